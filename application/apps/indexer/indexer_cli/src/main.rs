@@ -1416,6 +1416,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
                         attachment_count += 1;
                     }
                     Some((_, MessageStreamItem::Skipped)) => item_count += 1,
+                    Some((_, MessageStreamItem::Lost(_))) => err_count += 1,
                     Some((_, MessageStreamItem::Incomplete)) => err_count += 1,
                     Some((_, MessageStreamItem::Empty)) => err_count += 1,
                     Some((_, MessageStreamItem::Done)) => break,
@@ -1449,6 +1450,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
                                 consumed += used;
                             }
                             Some((_, MessageStreamItem::Skipped)) => skipped_count += 1,
+                            Some((_, MessageStreamItem::Lost(_))) => err_count += 1,
                             Some((_, MessageStreamItem::Incomplete)) => err_count += 1,
                             Some((_, MessageStreamItem::Empty)) => err_count += 1,
                             Some((_, MessageStreamItem::Done)) => break,
@@ -1502,6 +1504,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
                                 attachment_count += 1
                             }
                             Some((_, MessageStreamItem::Skipped)) => skipped_count += 1,
+                            Some((_, MessageStreamItem::Lost(_))) => err_count += 1,
                             Some((_, MessageStreamItem::Incomplete)) => err_count += 1,
                             Some((_, MessageStreamItem::Empty)) => err_count += 1,
                             Some((_, MessageStreamItem::Done)) => break,
@@ -1522,7 +1525,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
         }
         {
             println!("try text parser");
-            let txt_parser = StringTokenizer {};
+            let txt_parser = StringTokenizer::default();
             let buf_reader = BufReader::new(fs::File::open(input)?);
             let source = BinaryByteSource::new(buf_reader);
             let mut txt_msg_producer = MessageProducer::new(txt_parser, source, None);
@@ -1568,6 +1571,7 @@ async fn detect_messages_type(input: &Path) -> Result<bool, DltParseError> {
                         attachment_count += 1
                     }
                     Some((_, MessageStreamItem::Skipped)) => skipped_count += 1,
+                    Some((_, MessageStreamItem::Lost(_))) => err_count += 1,
                     Some((_, MessageStreamItem::Incomplete)) => err_count += 1,
                     Some((_, MessageStreamItem::Empty)) => err_count += 1,
                     Some((_, MessageStreamItem::Done)) => break,
@@ -25,6 +25,8 @@ use dlt_core::{
 };
 use log::trace;
 
+use crate::someip::SomeipParser;
+
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 use std::{
@@ -197,6 +199,8 @@ impl From<Option<&String>> for FormatOptions {
 pub struct FormattableMessage<'a> {
     pub message: Message,
     pub fibex_metadata: Option<&'a FibexMetadata>,
+    /// decodes SOME/IP messages carried by network traces
+    pub someip_parser: Option<&'a SomeipParser>,
     pub options: Option<&'a FormatOptions>,
 }
 
@@ -235,11 +239,13 @@ impl<'a> Serialize for FormattableMessage<'a> {
                 state.serialize_field("app-id", &ext_header_app_id)?;
                 state.serialize_field("context-id", &ext_header_context_id)?;
                 state.serialize_field("message-type", &ext_header_msg_type)?;
-                let arg_string = arguments
-                    .iter()
-                    .map(|a| DltArgument(a).to_string())
-                    .collect::<Vec<String>>()
-                    .join("|");
+                let arg_string = self.someip_message().unwrap_or_else(|| {
+                    arguments
+                        .iter()
+                        .map(|a| DltArgument(a).to_string())
+                        .collect::<Vec<String>>()
+                        .join("|")
+                });
                 state.serialize_field("payload", &arg_string)?;
             }
             PayloadContent::NonVerbose(id, data) => {
@@ -291,6 +297,7 @@ impl<'a> From<Message> for FormattableMessage<'a> {
         FormattableMessage {
             message,
             fibex_metadata: None,
+            someip_parser: None,
             options: None,
         }
     }
@@ -330,11 +337,13 @@ impl<'a> FormattableMessage<'a> {
         let eh_ctx_id: &str = ext_h_ctx_id.unwrap_or(empty);
         match &self.message.payload {
             PayloadContent::Verbose(arguments) => {
-                let arg_string = arguments
-                    .iter()
-                    .map(|a| DltArgument(a).to_string())
-                    .collect::<Vec<String>>()
-                    .join("|");
+                let arg_string = self.someip_message().unwrap_or_else(|| {
+                    arguments
+                        .iter()
+                        .map(|a| DltArgument(a).to_string())
+                        .collect::<Vec<String>>()
+                        .join("|")
+                });
                 Ok(PrintableMessage::new(
                     ext_h_app_id,
                     eh_ctx_id,
@@ -461,6 +470,41 @@ impl<'a> FormattableMessage<'a> {
         Ok(())
     }
 
+    /// The SOME/IP message carried by a network trace of the IPC or SOME/IP type,
+    /// decoded if a SOME/IP parser is given. The message is given by the raw
+    /// arguments; leading arguments might hold other information than the SOME/IP
+    /// header and are skipped, if the message cannot be decoded otherwise.
+    fn someip_message(&self) -> Option<String> {
+        let someip_parser = self.someip_parser?;
+        if !matches!(
+            self.message
+                .extended_header
+                .as_ref()
+                .map(|ext| &ext.message_type),
+            Some(MessageType::NetworkTrace(
+                NetworkTraceType::Ipc | NetworkTraceType::Someip
+            ))
+        ) {
+            return None;
+        }
+        let raw: Vec<&[u8]> = match &self.message.payload {
+            PayloadContent::Verbose(arguments) => arguments
+                .iter()
+                .filter_map(|arg| match &arg.value {
+                    Value::Raw(bytes) => Some(bytes.as_slice()),
+                    _ => None,
+                })
+                .collect(),
+            _ => return None,
+        };
+        (0..raw.len()).find_map(|skipped| {
+            someip_parser
+                .decode(&raw[skipped..].concat())
+                // the columns of the SOME/IP message are kept within the payload
+                .map(|decoded| decoded.replace(DLT_COLUMN_SENTINAL, " "))
+        })
+    }
+
     fn info_from_metadata<'b>(&'b self, id: u32, data: &[u8]) -> Option<NonVerboseInfo<'b>> {
         let fibex = self.fibex_metadata?;
         let md = extract_metadata(fibex, id, self.message.extended_header.as_ref())?;
@@ -547,6 +591,9 @@ impl<'a> fmt::Display for FormattableMessage<'a> {
         match &self.message.payload {
             PayloadContent::Verbose(arguments) => {
                 self.write_app_id_context_id_and_message_type(f)?;
+                if let Some(someip) = self.someip_message() {
+                    return write!(f, "{DLT_ARGUMENT_SENTINAL}{someip}");
+                }
                 arguments
                     .iter()
                     .try_for_each(|arg| write!(f, "{}{}", DLT_ARGUMENT_SENTINAL, DltArgument(arg)))
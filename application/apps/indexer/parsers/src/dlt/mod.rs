@@ -1,7 +1,10 @@
 pub mod attachment;
 pub mod fmt;
 
-use crate::{dlt::fmt::FormattableMessage, Error, LogMessage, ParseYield, Parser};
+use crate::{
+    dlt::fmt::FormattableMessage, resync_on_pattern, someip::SomeipParser, Error, LogMessage,
+    ParseYield, Parser,
+};
 use byteorder::{BigEndian, WriteBytesExt};
 pub use dlt_core::{
     dlt::LogLevel,
@@ -17,6 +20,11 @@ use std::{io::Write, ops::Range};
 
 use self::{attachment::FtScanner, fmt::FormatOptions};
 
+/// every dlt message stored in a file starts with a storage header with this pattern
+pub const STORAGE_HEADER_PATTERN: &[u8] = &[0x44, 0x4C, 0x54, 0x01]; // "DLT" + 0x01
+/// dlt messages sent over a serial line start with this header instead
+pub const SERIAL_HEADER_PATTERN: &[u8] = &[0x44, 0x4C, 0x53, 0x01]; // "DLS" + 0x01
+
 impl LogMessage for FormattableMessage<'_> {
     fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
         let bytes = self.message.as_bytes();
@@ -69,32 +77,82 @@ pub struct DltParser<'m> {
     pub filter_config: Option<ProcessedDltFilterConfig>,
     pub fibex_metadata: Option<&'m FibexMetadata>,
     pub fmt_options: Option<&'m FormatOptions>,
+    /// decodes SOME/IP messages carried by network traces
+    pub someip_parser: Option<&'m SomeipParser>,
     pub with_storage_header: bool,
+    pub with_serial_header: bool,
     ft_scanner: FtScanner,
     offset: usize,
 }
 
 #[derive(Default)]
 pub struct DltRangeParser {
+    pub with_serial_header: bool,
     offset: usize,
 }
 
 pub struct DltRawParser {
     pub with_storage_header: bool,
+    pub with_serial_header: bool,
 }
 
 impl DltRawParser {
     pub fn new(with_storage_header: bool) -> Self {
         Self {
             with_storage_header,
+            with_serial_header: false,
         }
     }
+
+    /// every message is expected to start with the serial header (`DLS` + 0x01),
+    /// which is kept as part of the raw message
+    pub fn with_serial_header(mut self, with_serial_header: bool) -> Self {
+        self.with_serial_header = with_serial_header;
+        self
+    }
 }
 
 impl DltRangeParser {
     pub fn new() -> Self {
-        Self { offset: 0 }
+        Self {
+            with_serial_header: false,
+            offset: 0,
+        }
+    }
+
+    /// every message is expected to start with the serial header (`DLS` + 0x01),
+    /// the ranges include the serial header
+    pub fn with_serial_header(mut self, with_serial_header: bool) -> Self {
+        self.with_serial_header = with_serial_header;
+        self
+    }
+}
+
+/// consumes a message with a serial header in front of its standard header and
+/// returns the length of both
+fn consume_serial_msg(input: &[u8]) -> Result<(&[u8], usize), Error> {
+    // the message length is part of the standard header (after header type and counter)
+    const LENGTH_OFFSET: usize = 2;
+    const MIN_STANDARD_HEADER_LENGTH: usize = 4;
+    let header_len = SERIAL_HEADER_PATTERN.len();
+    if input.len() < header_len + MIN_STANDARD_HEADER_LENGTH {
+        return Err(Error::Incomplete);
     }
+    if !input.starts_with(SERIAL_HEADER_PATTERN) {
+        return Err(Error::Parse("Missing serial header".to_owned()));
+    }
+    let length_pos = header_len + LENGTH_OFFSET;
+    let message_len = u16::from_be_bytes([input[length_pos], input[length_pos + 1]]) as usize;
+    if message_len < MIN_STANDARD_HEADER_LENGTH {
+        return Err(Error::Parse(format!(
+            "Invalid message length {message_len}"
+        )));
+    }
+    let len = header_len + message_len;
+    if input.len() < len {
+        return Err(Error::Incomplete);
+    }
+    Ok((&input[len..], len))
 }
 
 impl<'m> DltParser<'m> {
@@ -108,11 +166,27 @@ impl<'m> DltParser<'m> {
             filter_config,
             fibex_metadata,
             with_storage_header,
+            with_serial_header: false,
             fmt_options,
+            someip_parser: None,
             ft_scanner: FtScanner::new(),
             offset: 0,
         }
     }
+
+    /// every message is expected to start with the serial header (`DLS` + 0x01),
+    /// as used for dlt over serial lines. A storage header is not expected then.
+    pub fn with_serial_header(mut self, with_serial_header: bool) -> Self {
+        self.with_serial_header = with_serial_header;
+        self
+    }
+
+    /// SOME/IP messages of network traces (IPC and SOME/IP trace types) are decoded
+    /// and shown instead of their raw bytes
+    pub fn with_someip_parser(mut self, someip_parser: Option<&'m SomeipParser>) -> Self {
+        self.someip_parser = someip_parser;
+        self
+    }
 }
 
 impl<'m> Parser<FormattableMessage<'m>> for DltParser<'m> {
@@ -121,7 +195,18 @@ impl<'m> Parser<FormattableMessage<'m>> for DltParser<'m> {
         input: &'b [u8],
         timestamp: Option<u64>,
     ) -> Result<(&'b [u8], Option<ParseYield<FormattableMessage<'m>>>), Error> {
-        match dlt_message(input, self.filter_config.as_ref(), self.with_storage_header)
+        let (message, with_storage_header) = if self.with_serial_header {
+            if input.len() < SERIAL_HEADER_PATTERN.len() {
+                return Err(Error::Incomplete);
+            }
+            if !input.starts_with(SERIAL_HEADER_PATTERN) {
+                return Err(Error::Parse("Missing serial header".to_owned()));
+            }
+            (&input[SERIAL_HEADER_PATTERN.len()..], false)
+        } else {
+            (input, self.with_storage_header)
+        };
+        match dlt_message(message, self.filter_config.as_ref(), with_storage_header)
             .map_err(|e| Error::Parse(format!("{e}")))?
         {
             (rest, dlt_core::parse::ParsedMessage::FilteredOut(_n)) => {
@@ -142,6 +227,7 @@ impl<'m> Parser<FormattableMessage<'m>> for DltParser<'m> {
                 let msg = FormattableMessage {
                     message: msg_with_storage_header,
                     fibex_metadata: self.fibex_metadata,
+                    someip_parser: self.someip_parser,
                     options: self.fmt_options,
                 };
                 self.offset += input.len() - rest.len();
@@ -164,7 +250,11 @@ impl Parser<RangeMessage> for DltRangeParser {
         input: &'b [u8],
         _timestamp: Option<u64>,
     ) -> Result<(&'b [u8], Option<ParseYield<RangeMessage>>), Error> {
-        let (rest, consumed) = dlt_consume_msg(input).map_err(|e| Error::Parse(format!("{e}")))?;
+        let (rest, consumed) = if self.with_serial_header {
+            consume_serial_msg(input).map(|(rest, len)| (rest, Some(len as u64)))?
+        } else {
+            dlt_consume_msg(input).map_err(|e| Error::Parse(format!("{e}")))?
+        };
         let msg = consumed.map(|c| {
             self.offset += c as usize;
             RangeMessage {
@@ -176,6 +266,14 @@ impl Parser<RangeMessage> for DltRangeParser {
         });
         Ok((rest, msg.map(|m| m.into())))
     }
+
+    fn resync(&self, input: &[u8]) -> usize {
+        if self.with_serial_header {
+            resync_on_pattern(input, SERIAL_HEADER_PATTERN)
+        } else {
+            resync_on_pattern(input, STORAGE_HEADER_PATTERN)
+        }
+    }
 }
 
 impl Parser<RawMessage> for DltRawParser {
@@ -184,10 +282,57 @@ impl Parser<RawMessage> for DltRawParser {
         input: &'b [u8],
         _timestamp: Option<u64>,
     ) -> Result<(&'b [u8], Option<ParseYield<RawMessage>>), Error> {
-        let (rest, consumed) = dlt_consume_msg(input).map_err(|e| Error::Parse(format!("{e}")))?;
+        let (rest, consumed) = if self.with_serial_header {
+            consume_serial_msg(input).map(|(rest, len)| (rest, Some(len as u64)))?
+        } else {
+            dlt_consume_msg(input).map_err(|e| Error::Parse(format!("{e}")))?
+        };
         let msg = consumed.map(|c| RawMessage {
             content: Vec::from(&input[0..c as usize]),
         });
         Ok((rest, msg.map(|m| m.into())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serial_raw_and_range() {
+        let message: &[u8] = &[
+            0x44, 0x4C, 0x53, 0x01, // serial header
+            0x21, 0x00, 0x00, 0x08, // header type, counter, length (u16)
+            0x01, 0x02, 0x03, 0x04, // payload
+        ];
+        let mut input = message.to_vec();
+        input.extend_from_slice(message);
+
+        let mut raw = DltRawParser::new(false).with_serial_header(true);
+        let (rest, msg) = raw.parse(&input, None).unwrap();
+        match msg {
+            Some(ParseYield::Message(msg)) => assert_eq!(msg.content, message),
+            _ => panic!("no message"),
+        }
+        assert_eq!(rest, message);
+        assert!(matches!(
+            raw.parse(&message[..10], None),
+            Err(Error::Incomplete)
+        ));
+        assert!(matches!(
+            raw.parse(&message[4..], None),
+            Err(Error::Parse(_))
+        ));
+
+        let mut range = DltRangeParser::new().with_serial_header(true);
+        assert_eq!(range.resync(&input), message.len());
+        let (rest, _) = range.parse(&input, None).unwrap();
+        assert_eq!(rest, message);
+        let (rest, msg) = range.parse(rest, None).unwrap();
+        match msg {
+            Some(ParseYield::Message(msg)) => assert_eq!(msg.range.end, 2 * message.len()),
+            _ => panic!("no message"),
+        }
+        assert!(rest.is_empty());
+    }
+}
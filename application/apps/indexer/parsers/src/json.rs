@@ -0,0 +1,177 @@
+use crate::{Error, LogMessage, ParseYield, Parser, COLUMN_SEP, NEWLINE_SENTINAL};
+use memchr::memchr;
+use serde::Serialize;
+use serde_json::Value;
+use std::{fmt, io::Write};
+
+/// Parses newline delimited JSON (one object per line).
+/// The configured `columns` are paths into the object (see [`JsonMessage::value`])
+/// whose values are rendered as columns. Without columns the line is shown as it is.
+#[derive(Debug, Default)]
+pub struct JsonLinesParser {
+    columns: Vec<String>,
+}
+
+impl JsonLinesParser {
+    pub fn new(columns: Vec<String>) -> Self {
+        JsonLinesParser { columns }
+    }
+
+    fn message(&self, line: &[u8]) -> JsonMessage {
+        let raw = String::from_utf8_lossy(line)
+            .trim_end_matches('\r')
+            .to_owned();
+        let value = serde_json::from_str::<Value>(&raw).ok();
+        let description = match &value {
+            Some(value) if !self.columns.is_empty() => self
+                .columns
+                .iter()
+                .map(|path| lookup(value, path).map(column_string).unwrap_or_default())
+                .collect::<Vec<String>>()
+                .join(&COLUMN_SEP.to_string()),
+            // lines which are no valid JSON are shown as plain text
+            _ => raw.clone(),
+        };
+        JsonMessage {
+            raw,
+            value,
+            description,
+        }
+    }
+}
+
+impl Parser<JsonMessage> for JsonLinesParser {
+    fn parse<'a>(
+        &mut self,
+        input: &'a [u8],
+        _timestamp: Option<u64>,
+    ) -> Result<(&'a [u8], Option<ParseYield<JsonMessage>>), Error> {
+        if input.is_empty() {
+            return Ok((input, None));
+        }
+        let (line, rest) = match memchr(b'\n', input) {
+            Some(pos) => (&input[..pos], &input[pos + 1..]),
+            None => (input, &input[input.len()..]),
+        };
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok((rest, None));
+        }
+        Ok((rest, Some(ParseYield::from(self.message(line)))))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonMessage {
+    raw: String,
+    value: Option<Value>,
+    #[serde(skip)]
+    description: String,
+}
+
+impl JsonMessage {
+    /// the line as it was read from the source
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// the parsed object, `None` if the line isn't valid JSON
+    pub fn object(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// extracts a value by its path. Path segments are separated by `.`,
+    /// array elements are addressed by their index, e.g. `ctx.items.0.id`
+    pub fn value(&self, path: &str) -> Option<&Value> {
+        self.value.as_ref().and_then(|value| lookup(value, path))
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn column_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.replace('\n', &NEWLINE_SENTINAL.to_string()),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+    .replace(COLUMN_SEP, " ")
+}
+
+impl LogMessage for JsonMessage {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        writer.write_all(self.raw.as_bytes())?;
+        Ok(self.raw.len())
+    }
+}
+
+impl fmt::Display for JsonMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(parser: &mut JsonLinesParser, mut input: &[u8]) -> Vec<JsonMessage> {
+        let mut messages = vec![];
+        while !input.is_empty() {
+            let (rest, msg) = parser.parse(input, None).unwrap();
+            if let Some(ParseYield::Message(msg)) = msg {
+                messages.push(msg);
+            }
+            input = rest;
+        }
+        messages
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut parser = JsonLinesParser::new(vec![
+            "ts".to_owned(),
+            "level".to_owned(),
+            "ctx.items.1.id".to_owned(),
+            "msg".to_owned(),
+        ]);
+        let input = br#"{"ts":1,"level":"info","msg":"hello","ctx":{"items":[{"id":1},{"id":2}]}}
+{"ts":2,"msg":"two\nlines"}
+
+not json
+"#;
+        let messages = parse_all(&mut parser, input);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0].to_string().replace(COLUMN_SEP, "|"),
+            "1|info|2|hello"
+        );
+        assert_eq!(
+            messages[1].to_string().replace(COLUMN_SEP, "|"),
+            "2|||two\u{0006}lines"
+        );
+        assert_eq!(messages[2].to_string(), "not json");
+        assert!(messages[2].object().is_none());
+    }
+
+    #[test]
+    fn test_raw_and_path() {
+        let mut parser = JsonLinesParser::default();
+        let line = br#"{"a":{"b":[true,{"c":"x"}]}}"#;
+        let messages = parse_all(&mut parser, line);
+        let msg = &messages[0];
+        assert_eq!(msg.value("a.b.1.c"), Some(&Value::from("x")));
+        assert_eq!(msg.value("a.b.0"), Some(&Value::from(true)));
+        assert_eq!(msg.value("a.x"), None);
+        let mut exported = vec![];
+        msg.to_writer(&mut exported).unwrap();
+        assert_eq!(exported, line);
+    }
+}
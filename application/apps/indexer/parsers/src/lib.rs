@@ -1,13 +1,21 @@
 #![deny(unused_crate_dependencies)]
 pub mod dlt;
+pub mod json;
+pub mod pattern;
 pub mod someip;
+pub mod syslog;
 pub mod text;
 use serde::Serialize;
-use std::{fmt::Display, io::Write};
+use std::{fmt::Display, io::Write, ops::Range};
 use thiserror::Error;
 
 extern crate log;
 
+/// separates the columns of a message shown in a session
+pub(crate) const COLUMN_SEP: char = '\u{0004}';
+/// replaces line breaks inside of a message, they would break its row apart
+pub(crate) const NEWLINE_SENTINAL: char = '\u{0006}';
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Parse error: {0}")]
@@ -45,6 +53,53 @@ pub trait Parser<T> {
         input: &'a [u8],
         timestamp: Option<u64>,
     ) -> Result<(&'a [u8], Option<ParseYield<T>>), Error>;
+
+    /// after `parse` failed with a `Parse` error, find out how many bytes at the
+    /// beginning of `input` can be dropped before the next plausible message start.
+    ///
+    /// the default implementation advances byte by byte. Parsers that know how the
+    /// start of a message looks like (e.g. a magic pattern) should override this
+    /// to avoid trying every single offset.
+    fn resync(&self, input: &[u8]) -> usize {
+        input.len().min(1)
+    }
+
+    /// called once the underlying source has no more data. Parsers that keep parts
+    /// of messages internally can deliver them here, one per call until `None` is
+    /// returned.
+    fn finish(&mut self) -> Option<ParseYield<T>> {
+        None
+    }
+
+    /// called while the underlying source has no data for now but might provide more
+    /// later (e.g. a followed file or a stream). Parsers that hold back complete
+    /// messages in case they are continued can deliver them here, one per call until
+    /// `None` is returned.
+    fn flush(&mut self) -> Option<ParseYield<T>> {
+        None
+    }
+
+    /// `true` in case `flush` would deliver a message
+    fn has_pending(&self) -> bool {
+        false
+    }
+}
+
+/// returns the amount of bytes that can be dropped from `input` before `pattern` starts.
+/// The first byte is never considered a match since it is known to be broken.
+/// If `pattern` is not found, the last bytes are kept as they could be the beginning
+/// of a pattern that was not loaded completely yet.
+pub(crate) fn resync_on_pattern(input: &[u8], pattern: &[u8]) -> usize {
+    if input.len() <= 1 {
+        return input.len();
+    }
+    match memchr::memmem::find(&input[1..], pattern) {
+        Some(pos) => pos + 1,
+        None => input
+            .len()
+            .saturating_sub(pattern.len().saturating_sub(1))
+            .max(1),
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -88,7 +143,24 @@ pub trait LogMessage: Display + Serialize {
 pub enum MessageStreamItem<T: LogMessage> {
     Item(ParseYield<T>),
     Skipped,
+    /// bytes that had to be dropped to find the next valid message after a parse error.
+    /// The range is given in bytes consumed from the underlying byte source.
+    Lost(Range<usize>),
     Incomplete,
     Empty,
     Done,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resync_on_pattern() {
+        assert_eq!(resync_on_pattern(b"DLT\x01abcDLT\x01", b"DLT\x01"), 7);
+        assert_eq!(resync_on_pattern(b"xxDLT\x01", b"DLT\x01"), 2);
+        // a partial pattern at the end has to be kept
+        assert_eq!(resync_on_pattern(b"xxxxxDL", b"DLT\x01"), 4);
+        assert_eq!(resync_on_pattern(b"x", b"DLT\x01"), 1);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{Error, LogMessage, ParseYield, Parser, COLUMN_SEP};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use memchr::memchr;
+use regex::Regex;
+use serde::Serialize;
+use std::{fmt, io::Write};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PatternError {
+    #[error("Invalid pattern: {0}")]
+    Regex(#[from] regex::Error),
+    #[error("Pattern has no capture named \"{0}\"")]
+    UnknownCapture(String),
+}
+
+/// Parses lines of text by a user defined regular expression.
+/// Every named capture of the expression becomes a column, lines which
+/// don't match are shown as they are.
+#[derive(Debug)]
+pub struct RegexParser {
+    pattern: Regex,
+    timestamp: Option<Timestamp>,
+}
+
+#[derive(Debug)]
+struct Timestamp {
+    /// index of the timestamp capture within the columns
+    column: usize,
+    /// chrono format string, RFC 3339 or milliseconds since epoch are expected if not set
+    format: Option<String>,
+}
+
+impl RegexParser {
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        Ok(RegexParser {
+            pattern: Regex::new(pattern)?,
+            timestamp: None,
+        })
+    }
+
+    /// use the capture named `capture` as timestamp of the messages
+    pub fn with_timestamp(
+        mut self,
+        capture: &str,
+        format: Option<String>,
+    ) -> Result<Self, PatternError> {
+        let column = self
+            .columns()
+            .position(|name| name == capture)
+            .ok_or_else(|| PatternError::UnknownCapture(capture.to_owned()))?;
+        self.timestamp = Some(Timestamp { column, format });
+        Ok(self)
+    }
+
+    /// names of the columns in the order they are rendered
+    pub fn columns(&self) -> impl Iterator<Item = &str> {
+        self.pattern.capture_names().flatten()
+    }
+
+    /// index of the column holding the timestamp (if configured)
+    pub fn timestamp_column(&self) -> Option<usize> {
+        self.timestamp.as_ref().map(|ts| ts.column)
+    }
+
+    fn message(&self, line: &str) -> RegexMessage {
+        let captures = match self.pattern.captures(line) {
+            Some(captures) => captures,
+            None => {
+                return RegexMessage {
+                    raw: line.to_owned(),
+                    columns: None,
+                    timestamp: None,
+                }
+            }
+        };
+        let columns: Vec<String> = self
+            .columns()
+            .map(|name| {
+                captures
+                    .name(name)
+                    .map(|m| m.as_str().replace(COLUMN_SEP, " "))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let timestamp = self
+            .timestamp
+            .as_ref()
+            .and_then(|ts| parse_timestamp(&columns[ts.column], ts.format.as_deref()));
+        RegexMessage {
+            raw: line.to_owned(),
+            columns: Some(columns),
+            timestamp,
+        }
+    }
+}
+
+/// returns the timestamp in milliseconds since epoch. Timestamps without
+/// a timezone are taken as UTC.
+fn parse_timestamp(input: &str, format: Option<&str>) -> Option<i64> {
+    let input = input.trim();
+    match format {
+        Some(format) => DateTime::parse_from_str(input, format)
+            .map(|dt| dt.timestamp_millis())
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(input, format)
+                    .map(|dt| Utc.from_utc_datetime(&dt).timestamp_millis())
+            })
+            .ok(),
+        None => DateTime::parse_from_rfc3339(input)
+            .map(|dt| dt.timestamp_millis())
+            .ok()
+            .or_else(|| input.parse::<i64>().ok()),
+    }
+}
+
+impl Parser<RegexMessage> for RegexParser {
+    fn parse<'a>(
+        &mut self,
+        input: &'a [u8],
+        _timestamp: Option<u64>,
+    ) -> Result<(&'a [u8], Option<ParseYield<RegexMessage>>), Error> {
+        if input.is_empty() {
+            return Ok((input, None));
+        }
+        let (line, rest) = match memchr(b'\n', input) {
+            Some(pos) => (&input[..pos], &input[pos + 1..]),
+            None => (input, &input[input.len()..]),
+        };
+        let line = String::from_utf8_lossy(line);
+        let msg = self.message(line.trim_end_matches('\r'));
+        Ok((rest, Some(ParseYield::from(msg))))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegexMessage {
+    raw: String,
+    /// values of the captures, `None` if the line didn't match
+    columns: Option<Vec<String>>,
+    timestamp: Option<i64>,
+}
+
+impl RegexMessage {
+    /// value of the timestamp capture in milliseconds since epoch
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+}
+
+impl LogMessage for RegexMessage {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        writer.write_all(self.raw.as_bytes())?;
+        Ok(self.raw.len())
+    }
+}
+
+impl fmt::Display for RegexMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.columns {
+            Some(columns) => write!(f, "{}", columns.join(&COLUMN_SEP.to_string())),
+            None => write!(f, "{}", self.raw),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_captures() {
+        let mut parser =
+            RegexParser::new(r"^(?P<ts>\S+) (?P<level>\w+) (?:\[(?P<tag>\w+)\] )?(?P<msg>.*)$")
+                .unwrap()
+                .with_timestamp("ts", None)
+                .unwrap();
+        assert_eq!(
+            parser.columns().collect::<Vec<&str>>(),
+            vec!["ts", "level", "tag", "msg"]
+        );
+        assert_eq!(parser.timestamp_column(), Some(0));
+        let input =
+            b"2023-05-01T10:00:00Z INFO [net] link up\r\n2023-05-01T10:00:01Z WARN slow\ngarbage";
+        let (rest, first) = parser.parse(input, None).unwrap();
+        let first = match first {
+            Some(ParseYield::Message(msg)) => msg,
+            _ => panic!("no message"),
+        };
+        assert_eq!(
+            first.to_string().replace(COLUMN_SEP, "|"),
+            "2023-05-01T10:00:00Z|INFO|net|link up"
+        );
+        assert_eq!(first.timestamp(), Some(1682935200000));
+        let (rest, second) = parser.parse(rest, None).unwrap();
+        match second {
+            Some(ParseYield::Message(msg)) => assert_eq!(
+                msg.to_string().replace(COLUMN_SEP, "|"),
+                "2023-05-01T10:00:01Z|WARN||slow"
+            ),
+            _ => panic!("no message"),
+        }
+        let (rest, third) = parser.parse(rest, None).unwrap();
+        match third {
+            Some(ParseYield::Message(msg)) => {
+                assert_eq!(msg.to_string(), "garbage");
+                assert!(msg.columns().is_none());
+            }
+            _ => panic!("no message"),
+        }
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_timestamp_format() {
+        assert_eq!(
+            parse_timestamp("01.05.2023 10:00:00.250", Some("%d.%m.%Y %H:%M:%S%.3f")),
+            Some(1682935200250)
+        );
+        assert_eq!(parse_timestamp("1682935200250", None), Some(1682935200250));
+        assert!(RegexParser::new(r"(?P<a>\w+)")
+            .unwrap()
+            .with_timestamp("b", None)
+            .is_err());
+    }
+}
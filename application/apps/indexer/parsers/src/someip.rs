@@ -1,5 +1,16 @@
-use crate::{Error, LogMessage, ParseYield, Parser};
-use std::{borrow::Cow, fmt, fmt::Display, io::Write, path::PathBuf};
+pub mod filtering;
+pub mod statistic;
+
+use self::filtering::ProcessedSomeipFilterConfig;
+use crate::{Error, LogMessage, ParseYield, Parser, COLUMN_SEP};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    fmt::Display,
+    io::Write,
+    path::PathBuf,
+};
 
 use someip_messages::*;
 use someip_payload::{
@@ -8,12 +19,23 @@ use someip_payload::{
     som::SOMParser,
 };
 
-use log::{debug, error};
+use log::{debug, error, trace, warn};
 use serde::Serialize;
 
+/// flag of the message type, which marks a segment of a SOME/IP-TP message
+const TP_FLAG: u8 = 0x20;
+/// length of the SOME/IP-TP header following the SOME/IP header
+const TP_HEADER_LENGTH: usize = 4;
+/// segmented messages, which are reassembled at the same time; once exceeded, the
+/// oldest one is reported as incomplete
+const MAX_INCOMPLETE_TP_MESSAGES: usize = 64;
+
 /// A parser for SOME/IP log messages.
 pub struct SomeipParser {
     model: Option<FibexModel>,
+    /// messages not matching the configuration are dropped while parsing
+    filter_config: Option<ProcessedSomeipFilterConfig>,
+    segments: TpSegments,
 }
 
 impl Default for SomeipParser {
@@ -25,7 +47,7 @@ impl Default for SomeipParser {
 impl SomeipParser {
     /// Creates a new parser.
     pub fn new() -> Self {
-        SomeipParser { model: None }
+        Self::with_model(None)
     }
 
     /// Creates a new parser with the given files.
@@ -37,24 +59,130 @@ impl SomeipParser {
 
         if !readers.is_empty() {
             if let Ok(model) = FibexParser::try_parse(readers) {
-                return SomeipParser { model: Some(model) };
+                return Self::with_model(Some(model));
             }
         }
 
-        SomeipParser { model: None }
+        Self::with_model(None)
     }
-}
 
-unsafe impl Send for SomeipParser {}
-unsafe impl Sync for SomeipParser {}
+    /// Decodes a single SOME/IP message, which is embedded in another message (e.g.
+    /// a DLT network trace). `None` is returned, if it isn't a valid message.
+    pub fn decode(&self, input: &[u8]) -> Option<String> {
+        match Message::from_slice(input).ok()? {
+            Message::Sd(header, payload) => Some(sd_message_string(&header, &payload)),
+            Message::Rpc(header, payload) => {
+                Some(rpc_message_string(&header, &payload, self.model.as_ref()))
+            }
+            Message::CookieClient => Some(String::from("MCC")),
+            Message::CookieServer => Some(String::from("MCS")),
+        }
+    }
 
-impl Parser<SomeipLogMessage> for SomeipParser {
-    fn parse<'a>(
+    /// Only messages matching the filter configuration are parsed, all others are
+    /// skipped without yielding a message.
+    pub fn with_filter_config(
+        mut self,
+        filter_config: Option<ProcessedSomeipFilterConfig>,
+    ) -> Self {
+        self.filter_config = filter_config;
+        self
+    }
+
+    fn with_model(model: Option<FibexModel>) -> Self {
+        SomeipParser {
+            model,
+            filter_config: None,
+            segments: TpSegments::default(),
+        }
+    }
+
+    /// Skips a message, which is filtered out.
+    fn skip_message<'a>(
+        &self,
+        input: &'a [u8],
+        time: u64,
+    ) -> Result<(&'a [u8], Option<ParseYield<SomeipLogMessage>>), Error> {
+        let length = u32::from_be_bytes([input[4], input[5], input[6], input[7]]) as usize;
+        if length < 8 {
+            return Err(Error::Parse(format!(
+                "SOME/IP message with invalid length {length}"
+            )));
+        }
+        let len = length + 8;
+        if input.len() < len {
+            return Err(Error::Incomplete);
+        }
+        trace!("at {} : message filtered out ({} bytes)", time, len);
+        Ok((
+            if input.len() - len < Header::LENGTH {
+                &[0; 0]
+            } else {
+                &input[len..]
+            },
+            None,
+        ))
+    }
+
+    /// Adds a SOME/IP-TP segment to its message. Once all segments arrived, the
+    /// reassembled message is parsed.
+    fn parse_segment<'a>(
         &mut self,
         input: &'a [u8],
-        timestamp: Option<u64>,
+        time: u64,
+    ) -> Result<(&'a [u8], Option<ParseYield<SomeipLogMessage>>), Error> {
+        let length = u32::from_be_bytes([input[4], input[5], input[6], input[7]]) as usize;
+        if length < 8 + TP_HEADER_LENGTH {
+            return Err(Error::Parse(format!(
+                "SOME/IP-TP segment with invalid length {length}"
+            )));
+        }
+        let len = length + 8;
+        if input.len() < len {
+            return Err(Error::Incomplete);
+        }
+        let rest: &[u8] = if input.len() - len < Header::LENGTH {
+            &[0; 0]
+        } else {
+            &input[len..]
+        };
+        let reassembled = match self.segments.add(&input[..len]) {
+            Some(reassembled) => reassembled,
+            None => {
+                debug!("at {} : SOME/IP-TP segment ({} bytes)", time, len);
+                return Ok((
+                    rest,
+                    self.segments.reports.pop_front().map(ParseYield::from),
+                ));
+            }
+        };
+        debug!(
+            "at {} : SOME/IP-TP message reassembled ({} bytes)",
+            time,
+            reassembled.len()
+        );
+        let parsed = self
+            .parse_message(&reassembled, time)
+            .map(|(_, message)| message);
+        let message = match parsed {
+            Ok(message) => message,
+            Err(e) => Some(ParseYield::from(SomeipLogMessage::from(
+                format!(
+                    "TP{COLUMN_SEP}{}{COLUMN_SEP}Reassembled message cannot be parsed: {e}",
+                    raw_header_string(&reassembled)
+                ),
+                reassembled,
+            ))),
+        };
+        Ok((rest, message))
+    }
+
+    /// Parses a SOME/IP message, which isn't segmented.
+    fn parse_message<'a>(
+        &self,
+        input: &'a [u8],
+        time: u64,
     ) -> Result<(&'a [u8], Option<ParseYield<SomeipLogMessage>>), Error> {
-        let time = timestamp.unwrap_or(0);
         match Message::from_slice(input) {
             Ok(Message::Sd(header, payload)) => {
                 let len = header.message_len();
@@ -82,7 +210,7 @@ impl Parser<SomeipLogMessage> for SomeipParser {
                         &input[len..]
                     },
                     Some(ParseYield::from(SomeipLogMessage::from(
-                        rpc_message_string(&header, &payload, &self.model),
+                        rpc_message_string(&header, &payload, self.model.as_ref()),
                         input[..len].to_vec(),
                     ))),
                 ))
@@ -129,7 +257,198 @@ impl Parser<SomeipLogMessage> for SomeipParser {
     }
 }
 
-const COLUMN_SEP: char = '\u{0004}';
+unsafe impl Send for SomeipParser {}
+unsafe impl Sync for SomeipParser {}
+
+impl Parser<SomeipLogMessage> for SomeipParser {
+    fn parse<'a>(
+        &mut self,
+        input: &'a [u8],
+        timestamp: Option<u64>,
+    ) -> Result<(&'a [u8], Option<ParseYield<SomeipLogMessage>>), Error> {
+        if let Some(report) = self.segments.reports.pop_front() {
+            return Ok((input, Some(ParseYield::from(report))));
+        }
+        let time = timestamp.unwrap_or(0);
+        if input.len() >= Header::LENGTH
+            && self
+                .filter_config
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(input))
+        {
+            return self.skip_message(input, time);
+        }
+        if input.len() >= Header::LENGTH && input[14] & TP_FLAG != 0 {
+            return self.parse_segment(input, time);
+        }
+        self.parse_message(input, time)
+    }
+
+    fn resync(&self, input: &[u8]) -> usize {
+        for offset in 1..input.len() {
+            let candidate = &input[offset..];
+            if candidate.len() < Header::LENGTH || is_plausible_header(candidate) {
+                return offset;
+            }
+        }
+        input.len()
+    }
+
+    fn finish(&mut self) -> Option<ParseYield<SomeipLogMessage>> {
+        self.segments.report_all();
+        self.segments.reports.pop_front().map(ParseYield::from)
+    }
+}
+
+/// segments of a SOME/IP-TP message belong to each other if service, method,
+/// client and session are equal
+type TpKey = (u16, u16, u16, u16);
+
+#[derive(Debug)]
+struct TpMessage {
+    /// SOME/IP header of the first segment
+    header: Vec<u8>,
+    /// payload of the segments by their offset
+    segments: BTreeMap<usize, Vec<u8>>,
+    /// size of the payload, known once the last segment arrived
+    len: Option<usize>,
+}
+
+impl TpMessage {
+    /// the reassembled message if all segments arrived. The TP flag is removed and
+    /// the length covers the whole payload.
+    fn reassembled(&self) -> Option<Vec<u8>> {
+        let len = self.len?;
+        let mut message = self.header.clone();
+        message[4..8].copy_from_slice(&(len as u32 + 8).to_be_bytes());
+        message[14] &= !TP_FLAG;
+        for (offset, bytes) in self.segments.iter() {
+            let received = message.len() - Header::LENGTH;
+            if *offset > received {
+                return None;
+            }
+            let end = (offset + bytes.len()).min(len);
+            if end > received {
+                message.extend_from_slice(&bytes[received - offset..end - offset]);
+            }
+        }
+        (message.len() - Header::LENGTH == len).then_some(message)
+    }
+
+    /// ranges of the payload, which didn't arrive
+    fn missing(&self) -> Vec<String> {
+        let mut missing = vec![];
+        let mut received = 0;
+        for (offset, bytes) in self.segments.iter() {
+            if *offset > received {
+                missing.push(format!("{received}..{offset}"));
+            }
+            received = received.max(offset + bytes.len());
+        }
+        match self.len {
+            Some(len) if len > received => missing.push(format!("{received}..{len}")),
+            Some(_) => {}
+            None => missing.push(format!("{received}..")),
+        }
+        missing
+    }
+}
+
+/// Puts the segments of SOME/IP-TP messages together. Messages, which cannot be
+/// completed anymore, are reported.
+#[derive(Debug, Default)]
+struct TpSegments {
+    incomplete: HashMap<TpKey, TpMessage>,
+    /// incomplete messages in order of their first segment
+    order: VecDeque<TpKey>,
+    /// reports of incomplete messages, which are delivered before the next message
+    reports: VecDeque<SomeipLogMessage>,
+}
+
+impl TpSegments {
+    /// Adds a segment, given with its SOME/IP and SOME/IP-TP header. Segments might
+    /// arrive out of order; the message is provided once all segments arrived.
+    fn add(&mut self, segment: &[u8]) -> Option<Vec<u8>> {
+        let id = |pos: usize| u16::from_be_bytes([segment[pos], segment[pos + 1]]);
+        let key = (id(0), id(2), id(8), id(10));
+        let tp_header = u32::from_be_bytes([segment[16], segment[17], segment[18], segment[19]]);
+        let offset = (tp_header & 0xFFFF_FFF0) as usize;
+        let more_segments = tp_header & 0x01 != 0;
+        let payload = &segment[Header::LENGTH + TP_HEADER_LENGTH..];
+        if offset == 0
+            && self
+                .incomplete
+                .get(&key)
+                .is_some_and(|message| message.segments.contains_key(&0))
+        {
+            // the same session starts again, the former message remains incomplete
+            self.report(key);
+        }
+        if !self.incomplete.contains_key(&key) {
+            if self.order.len() >= MAX_INCOMPLETE_TP_MESSAGES {
+                if let Some(oldest) = self.order.front().copied() {
+                    self.report(oldest);
+                }
+            }
+            self.order.push_back(key);
+        }
+        let message = self.incomplete.entry(key).or_insert_with(|| TpMessage {
+            header: segment[..Header::LENGTH].to_vec(),
+            segments: BTreeMap::new(),
+            len: None,
+        });
+        if offset == 0 {
+            message.header = segment[..Header::LENGTH].to_vec();
+        }
+        if !more_segments {
+            message.len = Some(offset + payload.len());
+        }
+        message.segments.insert(offset, payload.to_vec());
+        let reassembled = message.reassembled()?;
+        self.incomplete.remove(&key);
+        self.order.retain(|pending| *pending != key);
+        Some(reassembled)
+    }
+
+    /// reports the message as incomplete and drops its segments
+    fn report(&mut self, key: TpKey) {
+        self.order.retain(|pending| *pending != key);
+        if let Some(message) = self.incomplete.remove(&key) {
+            let description = format!(
+                "TP{COLUMN_SEP}{}{COLUMN_SEP}Incomplete message, missing payload bytes {}",
+                raw_header_string(&message.header),
+                message.missing().join(", ")
+            );
+            warn!("SOME/IP-TP: {}", description.replace(COLUMN_SEP, " "));
+            self.reports
+                .push_back(SomeipLogMessage::from(description, message.header));
+        }
+    }
+
+    /// reports all messages, which are incomplete still
+    fn report_all(&mut self) {
+        while let Some(key) = self.order.front().copied() {
+            self.report(key);
+        }
+    }
+}
+
+/// Checks if the given bytes could be the start of a SOME/IP message.
+fn is_plausible_header(input: &[u8]) -> bool {
+    const PROTOCOL_VERSION: u8 = 0x01;
+    const MAX_RETURN_CODE: u8 = 0x5E;
+    if input.len() < Header::LENGTH {
+        return false;
+    }
+    let length = u32::from_be_bytes([input[4], input[5], input[6], input[7]]);
+    length >= 8
+        && input[12] == PROTOCOL_VERSION
+        && matches!(
+            input[14],
+            0x00 | 0x01 | 0x02 | 0x80 | 0x81 | 0x20 | 0x21 | 0x22 | 0xA0 | 0xA1
+        )
+        && input[15] <= MAX_RETURN_CODE
+}
 
 fn header_string(header: &Header) -> String {
     format!(
@@ -145,6 +464,22 @@ fn header_string(header: &Header) -> String {
     )
 }
 
+/// same as `header_string`, for a header given as bytes
+fn raw_header_string(header: &[u8]) -> String {
+    let field = |pos: usize| u16::from_be_bytes([header[pos], header[pos + 1]]);
+    format!(
+        "{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}",
+        field(0),
+        field(2),
+        u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+        field(8),
+        field(10),
+        header[13],
+        header[14],
+        header[15],
+    )
+}
+
 fn sd_message_string(header: &Header, payload: &SdPayload) -> String {
     let mut string = format!(
         "SD{}{}{}Flags: [{:02X?}]",
@@ -250,7 +585,7 @@ fn option_string(option: &SdEndpointOption) -> String {
     )
 }
 
-fn rpc_message_string(header: &Header, payload: &RpcPayload, model: &Option<FibexModel>) -> String {
+fn rpc_message_string(header: &Header, payload: &RpcPayload, model: Option<&FibexModel>) -> String {
     format!(
         "RPC{COLUMN_SEP}{}{COLUMN_SEP}{}",
         header_string(header),
@@ -397,6 +732,20 @@ mod test {
         FibexParser::parse(vec![reader]).expect("parse failed")
     }
 
+    #[test]
+    fn resync_to_next_header() {
+        let input: &[u8] = &[
+            0xDE, 0xAD, 0xBE, // garbage
+            0x01, 0x03, 0x80, 0x04, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x08, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+        ];
+
+        let parser = SomeipParser::new();
+        assert_eq!(parser.resync(input), 3);
+    }
+
     #[test]
     fn parse_cookie_client() {
         let input: &[u8] = &[
@@ -473,7 +822,7 @@ mod test {
 
         let model = test_model();
 
-        let mut parser = SomeipParser { model: Some(model) };
+        let mut parser = SomeipParser::with_model(Some(model));
         let (output, message) = parser.parse(input, None).unwrap();
 
         assert!(output.is_empty());
@@ -523,7 +872,7 @@ mod test {
 
         let model = test_model();
 
-        let mut parser = SomeipParser { model: Some(model) };
+        let mut parser = SomeipParser::with_model(Some(model));
         let (output, message) = parser.parse(input, None).unwrap();
 
         assert!(output.is_empty());
@@ -537,6 +886,73 @@ mod test {
         }
     }
 
+    #[test]
+    fn decode_embedded_message() {
+        let input: &[u8] = &[
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x0A, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0x01, 0x02, // payload([u8;2])
+        ];
+
+        let parser = SomeipParser::with_model(Some(test_model()));
+
+        let expected = r#"RPC|259|32773|10|1|2|1|2|0|TestService::testEvent {value1(UINT8):1,value2(UINT8):2,}"#;
+
+        assert_str(expected, &parser.decode(input).unwrap());
+        assert!(parser.decode(&input[..8]).is_none());
+    }
+
+    #[test]
+    fn skip_filtered_messages() {
+        let input: &[u8] = &[
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x0A, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0x01, 0x02, // payload([u8;2])
+            0xFF, 0xFF, 0x81, 0x00, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x14, // length(u32)
+            0x00, 0x00, 0x00, 0x00, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0xC0, 0x00, 0x00, 0x00, // sdFlags(08), reserved(u24)
+            0x00, 0x00, 0x00, 0x00, // entries-length(u32)
+            0x00, 0x00, 0x00, 0x00, // options-length(u32)
+        ];
+
+        let config = filtering::SomeipFilterConfig {
+            message_kind: Some(filtering::SomeipMessageKind::Sd),
+            ..Default::default()
+        };
+        let mut parser = SomeipParser::new().with_filter_config(Some((&config).into()));
+        let (output, message) = parser.parse(input, None).unwrap();
+
+        assert_eq!(output, &input[18..]);
+        assert!(message.is_none());
+
+        let (output, message) = parser.parse(output, None).unwrap();
+
+        assert!(output.is_empty());
+        assert!(message.is_some());
+
+        let config = filtering::SomeipFilterConfig {
+            service_ids: Some(vec![259]),
+            return_codes: Some(vec![0]),
+            ..Default::default()
+        };
+        let mut parser = SomeipParser::new().with_filter_config(Some((&config).into()));
+        let (output, message) = parser.parse(input, None).unwrap();
+
+        assert_eq!(output, &input[18..]);
+        assert!(message.is_some());
+
+        let (output, message) = parser.parse(output, None).unwrap();
+
+        assert!(output.is_empty());
+        assert!(message.is_none());
+    }
+
     #[test]
     fn parse_empty_sd_message() {
         let input: &[u8] = &[
@@ -604,4 +1020,84 @@ mod test {
             panic!("unexpected parse yield");
         }
     }
+
+    fn tp_segment(offset: u32, more_segments: bool, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+        ];
+        segment.extend_from_slice(&(payload.len() as u32 + 12).to_be_bytes());
+        segment.extend_from_slice(&[
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x22, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+        ]);
+        segment.extend_from_slice(&(offset | more_segments as u32).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    #[test]
+    fn parse_tp_segments_out_of_order() {
+        let payload: Vec<u8> = (0..20).collect();
+        let mut input = tp_segment(16, false, &payload[16..]);
+        input.extend_from_slice(&tp_segment(0, true, &payload[..16]));
+
+        let mut parser = SomeipParser::new();
+        let (output, message) = parser.parse(&input, None).unwrap();
+
+        assert_eq!(output.len(), 36);
+        assert!(message.is_none());
+
+        let (output, message) = parser.parse(output, None).unwrap();
+
+        assert!(output.is_empty());
+
+        let expected = r#"RPC|259|32773|28|1|2|1|2|0|Bytes: [00, 01, 02, 03, 04, 05, 06, 07, 08, 09, 0A, 0B, 0C, 0D, 0E, 0F, 10, 11, 12, 13]"#;
+
+        if let ParseYield::Message(item) = message.unwrap() {
+            assert_str(expected, &format!("{}", item));
+        } else {
+            panic!("unexpected parse yield");
+        }
+    }
+
+    #[test]
+    fn report_incomplete_tp_segments() {
+        let first = tp_segment(0, true, &[0; 16]);
+
+        let mut parser = SomeipParser::new();
+        let (output, message) = parser.parse(&first, None).unwrap();
+
+        assert!(output.is_empty());
+        assert!(message.is_none());
+
+        // the session starts again before the former message was completed
+        let (output, message) = parser.parse(&first, None).unwrap();
+
+        assert!(output.is_empty());
+
+        let expected =
+            r#"TP|259|32773|28|1|2|1|34|0|Incomplete message, missing payload bytes 16.."#;
+
+        if let ParseYield::Message(item) = message.unwrap() {
+            assert_str(expected, &format!("{}", item));
+        } else {
+            panic!("unexpected parse yield");
+        }
+
+        let last = tp_segment(32, false, &[0; 4]);
+        let (_, message) = parser.parse(&last, None).unwrap();
+
+        assert!(message.is_none());
+
+        let expected =
+            r#"TP|259|32773|28|1|2|1|34|0|Incomplete message, missing payload bytes 16..32"#;
+
+        if let ParseYield::Message(item) = parser.finish().unwrap() {
+            assert_str(expected, &format!("{}", item));
+        } else {
+            panic!("unexpected parse yield");
+        }
+
+        assert!(parser.finish().is_none());
+    }
 }
@@ -0,0 +1,92 @@
+//! # Filtering of SOME/IP messages while they're parsed
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, hash::Hash};
+
+use super::TP_FLAG;
+
+/// service id and method id of service discovery messages
+const SD_SERVICE_ID: u16 = 0xFFFF;
+const SD_METHOD_ID: u16 = 0x8100;
+
+/// Kind of SOME/IP messages to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SomeipMessageKind {
+    /// service discovery messages
+    Sd,
+    /// all messages other than service discovery
+    Rpc,
+}
+
+/// Configuration of the SOME/IP messages to keep, all others are filtered out.
+/// Criteria which are not given match any message.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SomeipFilterConfig {
+    pub service_ids: Option<Vec<u16>>,
+    pub method_ids: Option<Vec<u16>>,
+    /// message types, segments of SOME/IP-TP messages match their unsegmented type
+    pub message_types: Option<Vec<u8>>,
+    pub return_codes: Option<Vec<u8>>,
+    pub message_kind: Option<SomeipMessageKind>,
+}
+
+/// The filter configuration with ids in sets, as used while parsing
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedSomeipFilterConfig {
+    pub service_ids: Option<HashSet<u16>>,
+    pub method_ids: Option<HashSet<u16>>,
+    pub message_types: Option<HashSet<u8>>,
+    pub return_codes: Option<HashSet<u8>>,
+    pub message_kind: Option<SomeipMessageKind>,
+}
+
+impl From<&SomeipFilterConfig> for ProcessedSomeipFilterConfig {
+    fn from(config: &SomeipFilterConfig) -> Self {
+        ProcessedSomeipFilterConfig {
+            service_ids: config
+                .service_ids
+                .as_ref()
+                .map(|ids| ids.iter().copied().collect()),
+            method_ids: config
+                .method_ids
+                .as_ref()
+                .map(|ids| ids.iter().copied().collect()),
+            message_types: config
+                .message_types
+                .as_ref()
+                .map(|types| types.iter().copied().collect()),
+            return_codes: config
+                .return_codes
+                .as_ref()
+                .map(|codes| codes.iter().copied().collect()),
+            message_kind: config.message_kind,
+        }
+    }
+}
+
+impl ProcessedSomeipFilterConfig {
+    /// Checks the header of a message, which has to be complete, against the
+    /// configuration.
+    pub fn matches(&self, header: &[u8]) -> bool {
+        let service_id = u16::from_be_bytes([header[0], header[1]]);
+        let method_id = u16::from_be_bytes([header[2], header[3]]);
+        let is_sd = service_id == SD_SERVICE_ID && method_id == SD_METHOD_ID;
+        allows(&self.service_ids, &service_id)
+            && allows(&self.method_ids, &method_id)
+            && allows(&self.message_types, &(header[14] & !TP_FLAG))
+            && allows(&self.return_codes, &header[15])
+            && match self.message_kind {
+                Some(SomeipMessageKind::Sd) => is_sd,
+                Some(SomeipMessageKind::Rpc) => !is_sd,
+                None => true,
+            }
+    }
+}
+
+/// ids are allowed if they're contained or if no ids are given at all
+fn allows<T: Eq + Hash>(ids: &Option<HashSet<T>>, id: &T) -> bool {
+    match ids {
+        Some(ids) => ids.contains(id),
+        None => true,
+    }
+}
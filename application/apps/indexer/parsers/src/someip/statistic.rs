@@ -0,0 +1,340 @@
+//! # Statistic of SOME/IP messages, e.g. to choose what to observe of a trace
+use serde::Serialize;
+use someip_messages::*;
+
+use super::{is_plausible_header, option_string};
+
+/// messages of the statistic are expected to be within a packet or a few TCP
+/// segments, longer messages are assumed to be a wrong start of a message
+const MAX_MESSAGE_LENGTH: usize = 0x10000;
+
+/// Count of messages with a certain id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatisticDetail {
+    pub id: u16,
+    pub num: usize,
+}
+
+/// Count of messages with a certain id, along with the counts of its details (e.g.
+/// the methods of a service)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatisticItem {
+    pub item: StatisticDetail,
+    pub details: Vec<StatisticDetail>,
+}
+
+/// Entries of the service discovery for a service instance
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InstanceStatistic {
+    pub service_id: u16,
+    pub instance_id: u16,
+    pub finds: usize,
+    pub offers: usize,
+    pub stop_offers: usize,
+    pub subscriptions: usize,
+    pub stop_subscriptions: usize,
+    pub subscription_acks: usize,
+    pub subscription_nacks: usize,
+    /// eventgroups, which were subscribed
+    pub eventgroups: Vec<u16>,
+    /// endpoints given by the entries of the instance
+    pub endpoints: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SomeipStatistic {
+    /// messages per service, with the details per method or event
+    pub services: Vec<StatisticItem>,
+    /// messages per message type, with the details per return code
+    pub messages: Vec<StatisticItem>,
+    /// service discovery entries per service instance
+    pub instances: Vec<InstanceStatistic>,
+    /// all endpoints given by service discovery entries
+    pub endpoints: Vec<String>,
+}
+
+impl SomeipStatistic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the messages of the input. The count of bytes of all complete
+    /// messages is returned, the remaining bytes might be completed by further
+    /// input. Bytes which cannot be the start of a message are skipped.
+    pub fn collect(&mut self, input: &[u8]) -> usize {
+        let mut consumed = 0;
+        while input.len() - consumed >= Header::LENGTH {
+            let message = &input[consumed..];
+            if !is_plausible_header(message) {
+                consumed += 1;
+                continue;
+            }
+            let length = u32::from_be_bytes([message[4], message[5], message[6], message[7]]);
+            let len = length as usize + 8;
+            if len > MAX_MESSAGE_LENGTH {
+                consumed += 1;
+                continue;
+            }
+            if message.len() < len {
+                break;
+            }
+            self.count(&message[..len]);
+            consumed += len;
+        }
+        consumed
+    }
+
+    /// adds the counts of another statistic
+    pub fn merge(&mut self, other: SomeipStatistic) {
+        for (own, others) in [
+            (&mut self.services, other.services),
+            (&mut self.messages, other.messages),
+        ] {
+            for item in others {
+                for detail in item.details {
+                    count(own, item.item.id, detail.id, detail.num);
+                }
+            }
+        }
+        for other in other.instances {
+            let instance = instance(&mut self.instances, other.service_id, other.instance_id);
+            instance.finds += other.finds;
+            instance.offers += other.offers;
+            instance.stop_offers += other.stop_offers;
+            instance.subscriptions += other.subscriptions;
+            instance.stop_subscriptions += other.stop_subscriptions;
+            instance.subscription_acks += other.subscription_acks;
+            instance.subscription_nacks += other.subscription_nacks;
+            for eventgroup in other.eventgroups {
+                insert(&mut instance.eventgroups, eventgroup);
+            }
+            for endpoint in other.endpoints {
+                insert(&mut instance.endpoints, endpoint);
+            }
+        }
+        for endpoint in other.endpoints {
+            insert(&mut self.endpoints, endpoint);
+        }
+    }
+
+    fn count(&mut self, message: &[u8]) {
+        let field = |pos: usize| u16::from_be_bytes([message[pos], message[pos + 1]]);
+        count(&mut self.services, field(0), field(2), 1);
+        count(
+            &mut self.messages,
+            message[14] as u16,
+            message[15] as u16,
+            1,
+        );
+        if let Ok(Message::Sd(_, payload)) = Message::from_slice(message) {
+            self.count_sd_entries(&payload);
+        }
+    }
+
+    fn count_sd_entries(&mut self, payload: &SdPayload) {
+        for (i, entry) in payload.entries.iter().enumerate() {
+            let instance = match entry {
+                SdEntry::FindService(value) | SdEntry::OfferService(value) => {
+                    instance(&mut self.instances, value.service_id, value.instance_id)
+                }
+                SdEntry::SubscribeEventgroup(value) | SdEntry::SubscribeEventgroupAck(value) => {
+                    instance(&mut self.instances, value.service_id, value.instance_id)
+                }
+            };
+            match entry {
+                SdEntry::FindService(_) => instance.finds += 1,
+                SdEntry::OfferService(value) if value.has_ttl() => instance.offers += 1,
+                SdEntry::OfferService(_) => instance.stop_offers += 1,
+                SdEntry::SubscribeEventgroup(value) if value.has_ttl() => {
+                    instance.subscriptions += 1;
+                    insert(&mut instance.eventgroups, value.eventgroup_id);
+                }
+                SdEntry::SubscribeEventgroup(_) => instance.stop_subscriptions += 1,
+                SdEntry::SubscribeEventgroupAck(value) if value.has_ttl() => {
+                    instance.subscription_acks += 1
+                }
+                SdEntry::SubscribeEventgroupAck(_) => instance.subscription_nacks += 1,
+            }
+            for option in payload.options(i) {
+                let endpoint = option_string(option);
+                insert(&mut instance.endpoints, endpoint.clone());
+                insert(&mut self.endpoints, endpoint);
+            }
+        }
+    }
+}
+
+/// adds `num` messages to the item and its detail, items and details are sorted by id
+fn count(items: &mut Vec<StatisticItem>, id: u16, detail_id: u16, num: usize) {
+    let pos = match items.binary_search_by_key(&id, |item| item.item.id) {
+        Ok(pos) => pos,
+        Err(pos) => {
+            items.insert(
+                pos,
+                StatisticItem {
+                    item: StatisticDetail { id, num: 0 },
+                    details: vec![],
+                },
+            );
+            pos
+        }
+    };
+    let item = &mut items[pos];
+    item.item.num += num;
+    match item
+        .details
+        .binary_search_by_key(&detail_id, |detail| detail.id)
+    {
+        Ok(pos) => item.details[pos].num += num,
+        Err(pos) => item
+            .details
+            .insert(pos, StatisticDetail { id: detail_id, num }),
+    }
+}
+
+/// the statistic of a service instance, instances are sorted by service and instance
+fn instance(
+    instances: &mut Vec<InstanceStatistic>,
+    service_id: u16,
+    instance_id: u16,
+) -> &mut InstanceStatistic {
+    let pos = match instances.binary_search_by_key(&(service_id, instance_id), |instance| {
+        (instance.service_id, instance.instance_id)
+    }) {
+        Ok(pos) => pos,
+        Err(pos) => {
+            instances.insert(
+                pos,
+                InstanceStatistic {
+                    service_id,
+                    instance_id,
+                    ..Default::default()
+                },
+            );
+            pos
+        }
+    };
+    &mut instances[pos]
+}
+
+/// inserts the value into the sorted values, if it's not contained yet
+fn insert<T: Ord>(values: &mut Vec<T>, value: T) {
+    if let Err(pos) = values.binary_search(&value) {
+        values.insert(pos, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_messages() -> Vec<u8> {
+        vec![
+            // RPC message
+            0x01, 0x03, 0x80, 0x05, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x0A, // length(u32)
+            0x00, 0x01, 0x00, 0x02, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0x01, 0x02, // payload([u8;2])
+            // SD message
+            0xFF, 0xFF, 0x81, 0x00, // serviceId(u16), methodId(u16)
+            0x00, 0x00, 0x00, 0x40, // length(u32)
+            0x00, 0x00, 0x00, 0x00, // clientId(u16), sessionId(u16)
+            0x01, 0x01, 0x02, 0x00, // proto(u8), version(u8), messageType,(u8) returnCode(u8)
+            0xC0, 0x00, 0x00, 0x00, // sdFlags(08), reserved(u24)
+            // entries
+            0x00, 0x00, 0x00, 0x20, // entries-length(u32)
+            // subscribe-eventgroup
+            0x06, 0x00, 0x00, 0x00, // entryType(u8), index1(u8), index2,(u8) num1|2(u8)
+            0x01, 0x03, 0x00, 0x01, // serviceId(u16), instanceId(u16)
+            0x02, 0x00, 0x00, 0x03, // majorVersion(u8), ttl(u24)
+            0x00, 0x00, 0x01, 0xC8, // reserved(u16), eventgroupId(u16)
+            // subscribe-eventgroup-ack
+            0x07, 0x00, 0x00, 0x10, // entryType(u8), index1(u8), index2,(u8) num1|2(u8)
+            0x01, 0x03, 0x00, 0x01, // serviceId(u16), instanceId(u16)
+            0x02, 0x00, 0x00, 0x03, // majorVersion(u8), ttl(u24)
+            0x00, 0x00, 0x01, 0xC8, // reserved(u16), eventgroupId(u16)
+            // options
+            0x00, 0x00, 0x00, 0x0C, // options-length(u32)
+            // ip-4 endpoint
+            0x00, 0x09, 0x04, 0x00, // length(u16), optionType(u8), reserved(u8)
+            0x7F, 0x00, 0x00, 0x01, // ip4(u32)
+            0x00, 0x11, 0x75, 0x30, // reserved(u8), proto(u8), port(u16)
+        ]
+    }
+
+    #[test]
+    fn collect_statistic() {
+        let input = sample_messages();
+
+        let mut statistic = SomeipStatistic::new();
+        // the last message is incomplete
+        assert_eq!(statistic.collect(&input[..input.len() - 1]), 18);
+        assert_eq!(statistic.collect(&input[18..]), input.len() - 18);
+
+        let mut merged = statistic.clone();
+        merged.merge(statistic);
+
+        assert_eq!(
+            merged.services,
+            vec![
+                StatisticItem {
+                    item: StatisticDetail { id: 259, num: 2 },
+                    details: vec![StatisticDetail { id: 32773, num: 2 }],
+                },
+                StatisticItem {
+                    item: StatisticDetail { id: 65535, num: 2 },
+                    details: vec![StatisticDetail { id: 33024, num: 2 }],
+                },
+            ]
+        );
+        assert_eq!(
+            merged.messages,
+            vec![StatisticItem {
+                item: StatisticDetail { id: 2, num: 4 },
+                details: vec![StatisticDetail { id: 0, num: 4 }],
+            }]
+        );
+        assert_eq!(
+            merged.instances,
+            vec![InstanceStatistic {
+                service_id: 259,
+                instance_id: 1,
+                subscriptions: 2,
+                subscription_acks: 2,
+                eventgroups: vec![456],
+                endpoints: vec![String::from("UDP 127.0.0.1:30000")],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(merged.endpoints, vec![String::from("UDP 127.0.0.1:30000")]);
+    }
+
+    #[test]
+    fn collect_after_other_data() {
+        // a length which is larger than the whole input must not hold back the messages
+        let mut input = vec![
+            0x12, 0x34, 0x56, 0x78, // no SOME/IP message
+            0x7F, 0xFF, 0xFF, 0xFF, //
+            0x00, 0x00, 0x00, 0x00, //
+            0x45, 0x00, 0x00, 0x00, //
+        ];
+        input.extend(sample_messages());
+
+        let mut statistic = SomeipStatistic::new();
+        assert_eq!(statistic.collect(&input), input.len());
+        assert_eq!(
+            statistic.services,
+            vec![
+                StatisticItem {
+                    item: StatisticDetail { id: 259, num: 1 },
+                    details: vec![StatisticDetail { id: 32773, num: 1 }],
+                },
+                StatisticItem {
+                    item: StatisticDetail { id: 65535, num: 1 },
+                    details: vec![StatisticDetail { id: 33024, num: 1 }],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Parser for syslog messages as described in RFC 5424 and the older
+//! BSD format of RFC 3164. Every line (or datagram) is expected to hold one message.
+//! Sources receiving messages from several senders can put the address of the
+//! sender in front of the message: `@{address} {message}`.
+//! Lines which are no syslog messages are passed on as they are: only `msg` (and
+//! the sender) is set then.
+use crate::{dlt::LogLevel, Error, LogMessage, ParseYield, Parser, COLUMN_SEP};
+use memchr::memchr;
+use serde::Serialize;
+use std::{fmt, io::Write};
+
+/// marks the address of the sender in front of a message
+pub const SENDER_PREFIX: char = '@';
+
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Default)]
+pub struct SyslogParser {}
+
+impl SyslogParser {
+    pub fn new() -> Self {
+        SyslogParser {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyslogMessage {
+    /// address of the sender if provided by the source
+    pub sender: Option<String>,
+    /// `None` if the line is no syslog message
+    pub facility: Option<u8>,
+    /// `None` if the line is no syslog message
+    pub severity: Option<u8>,
+    /// protocol version, only given for RFC 5424 messages
+    pub version: Option<u8>,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub structured_data: Option<String>,
+    pub msg: String,
+    #[serde(skip)]
+    raw: String,
+}
+
+impl SyslogMessage {
+    pub fn facility_name(&self) -> Option<&'static str> {
+        self.facility
+            .and_then(|facility| FACILITIES.get(facility as usize).copied())
+    }
+
+    pub fn severity_name(&self) -> Option<&'static str> {
+        self.severity
+            .and_then(|severity| SEVERITIES.get(severity as usize).copied())
+    }
+
+    /// maps the syslog severity onto the log levels used for DLT
+    pub fn log_level(&self) -> Option<LogLevel> {
+        self.severity.map(|severity| match severity {
+            0..=2 => LogLevel::Fatal,
+            3 => LogLevel::Error,
+            4 => LogLevel::Warn,
+            5 | 6 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        })
+    }
+
+    /// a line which is no syslog message
+    fn unparsed(line: &str) -> Self {
+        SyslogMessage {
+            sender: None,
+            facility: None,
+            severity: None,
+            version: None,
+            timestamp: None,
+            hostname: None,
+            app_name: None,
+            proc_id: None,
+            msg_id: None,
+            structured_data: None,
+            msg: line.to_owned(),
+            raw: line.to_owned(),
+        }
+    }
+}
+
+impl LogMessage for SyslogMessage {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        writer.write_all(self.raw.as_bytes())?;
+        Ok(self.raw.len())
+    }
+}
+
+impl fmt::Display for SyslogMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = |value: &Option<String>| -> String {
+            value
+                .as_deref()
+                .unwrap_or_default()
+                .replace(COLUMN_SEP, " ")
+        };
+        write!(
+            f,
+            "{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}{COLUMN_SEP}{}",
+            column(&self.sender),
+            self.facility_name().unwrap_or_default(),
+            self.severity_name().unwrap_or_default(),
+            column(&self.timestamp),
+            column(&self.hostname),
+            column(&self.app_name),
+            column(&self.proc_id),
+            column(&self.msg_id),
+            column(&self.structured_data),
+            self.msg.replace(COLUMN_SEP, " "),
+        )
+    }
+}
+
+impl Parser<SyslogMessage> for SyslogParser {
+    fn parse<'a>(
+        &mut self,
+        input: &'a [u8],
+        _timestamp: Option<u64>,
+    ) -> Result<(&'a [u8], Option<ParseYield<SyslogMessage>>), Error> {
+        if input.is_empty() {
+            return Ok((input, None));
+        }
+        let (line, rest) = match memchr(b'\n', input) {
+            Some(pos) => (&input[..pos], &input[pos + 1..]),
+            None => (input, &input[input.len()..]),
+        };
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\0']);
+        if line.trim().is_empty() {
+            return Ok((rest, None));
+        }
+        Ok((rest, Some(ParseYield::from(parse_message(line)))))
+    }
+}
+
+fn nil_or(value: &str) -> Option<String> {
+    if value == "-" || value.is_empty() {
+        None
+    } else {
+        Some(value.to_owned())
+    }
+}
+
+/// parses `<PRI>` and returns facility, severity and the remaining input
+fn parse_pri(input: &str) -> Option<(u8, u8, &str)> {
+    let input = input.strip_prefix('<')?;
+    let end = input.find('>')?;
+    let digits = &input[..end];
+    if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let pri: u8 = digits.parse().ok().filter(|pri| *pri <= 191)?;
+    Some((pri >> 3, pri & 0x07, &input[end + 1..]))
+}
+
+fn parse_message(line: &str) -> SyslogMessage {
+    let (sender, line) = match line
+        .strip_prefix(SENDER_PREFIX)
+        .and_then(|line| line.split_once(' '))
+    {
+        Some((sender, line)) => (nil_or(sender), line),
+        None => (None, line),
+    };
+    let mut msg = parse_syslog(line).unwrap_or_else(|| SyslogMessage::unparsed(line));
+    msg.sender = sender;
+    msg
+}
+
+fn parse_syslog(line: &str) -> Option<SyslogMessage> {
+    let (facility, severity, rest) = parse_pri(line)?;
+    let mut msg = match rest.split_once(' ') {
+        Some((version, rest))
+            if !version.is_empty()
+                && version.len() <= 2
+                && version.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            parse_rfc5424(version.parse().ok()?, rest)?
+        }
+        _ => parse_rfc3164(rest),
+    };
+    msg.facility = Some(facility);
+    msg.severity = Some(severity);
+    msg.raw = line.to_owned();
+    Some(msg)
+}
+
+fn parse_rfc5424(version: u8, input: &str) -> Option<SyslogMessage> {
+    let mut fields = input.splitn(6, ' ');
+    let timestamp = nil_or(fields.next()?);
+    let hostname = nil_or(fields.next()?);
+    let app_name = nil_or(fields.next()?);
+    let proc_id = nil_or(fields.next()?);
+    let msg_id = nil_or(fields.next()?);
+    let rest = fields.next().unwrap_or_default();
+    let (structured_data, msg) = if let Some(rest) = rest.strip_prefix('-') {
+        (None, rest)
+    } else {
+        let len = structured_data_len(rest)?;
+        (Some(rest[..len].to_owned()), &rest[len..])
+    };
+    let msg = msg.strip_prefix(' ').unwrap_or(msg);
+    Some(SyslogMessage {
+        sender: None,
+        facility: None,
+        severity: None,
+        version: Some(version),
+        timestamp,
+        hostname,
+        app_name,
+        proc_id,
+        msg_id,
+        structured_data,
+        msg: msg.trim_start_matches('\u{FEFF}').to_owned(),
+        raw: String::new(),
+    })
+}
+
+/// length of the structured data elements (`[id key="value"]...`) at the
+/// beginning of `input`
+fn structured_data_len(input: &str) -> Option<usize> {
+    let mut in_element = false;
+    let mut in_value = false;
+    let mut escaped = false;
+    let mut len = 0;
+    for (pos, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_value => escaped = true,
+            '"' if in_element => in_value = !in_value,
+            '[' if !in_element => in_element = true,
+            ']' if in_element && !in_value => {
+                in_element = false;
+                len = pos + 1;
+            }
+            _ if !in_element => break,
+            _ => {}
+        }
+    }
+    if len == 0 {
+        None
+    } else {
+        Some(len)
+    }
+}
+
+/// BSD syslog: `Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG`. All parts except
+/// the message are optional in practice.
+fn parse_rfc3164(input: &str) -> SyslogMessage {
+    let mut msg = SyslogMessage {
+        sender: None,
+        facility: None,
+        severity: None,
+        version: None,
+        timestamp: None,
+        hostname: None,
+        app_name: None,
+        proc_id: None,
+        msg_id: None,
+        structured_data: None,
+        msg: String::new(),
+        raw: String::new(),
+    };
+    let mut rest = input;
+    if is_rfc3164_timestamp(rest) {
+        msg.timestamp = Some(rest[..15].to_owned());
+        rest = &rest[16..];
+        // the hostname is missing if the first word already is the tag
+        if let Some((hostname, after)) = rest.split_once(' ') {
+            if !hostname.ends_with(':') && !hostname.contains('[') {
+                msg.hostname = nil_or(hostname);
+                rest = after;
+            }
+        }
+    }
+    if let Some(tag_end) = rest.find([':', '[', ' ']) {
+        let tag = &rest[..tag_end];
+        let after = &rest[tag_end..];
+        let (proc_id, after) = match after.strip_prefix('[').and_then(|a| a.split_once(']')) {
+            Some((proc_id, after)) => (nil_or(proc_id), after),
+            None => (None, after),
+        };
+        if let Some(after) = after.strip_prefix(':') {
+            msg.app_name = nil_or(tag);
+            msg.proc_id = proc_id;
+            rest = after.strip_prefix(' ').unwrap_or(after);
+        }
+    }
+    msg.msg = rest.to_owned();
+    msg
+}
+
+fn is_rfc3164_timestamp(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    bytes.len() > 15
+        && MONTHS.iter().any(|month| input.starts_with(month))
+        && bytes[3] == b' '
+        && bytes[6] == b' '
+        && bytes[9] == b':'
+        && bytes[12] == b':'
+        && bytes[15] == b' '
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_line(line: &str) -> SyslogMessage {
+        match SyslogParser::new().parse(line.as_bytes(), None) {
+            Ok((_, Some(ParseYield::Message(msg)))) => msg,
+            _ => panic!("no message parsed from {line}"),
+        }
+    }
+
+    #[test]
+    fn test_rfc5424() {
+        let msg = parse_line(
+            r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="App\]lication"][x@1 a="b"] An application event"#,
+        );
+        assert_eq!(msg.facility_name(), Some("local4"));
+        assert_eq!(msg.severity_name(), Some("notice"));
+        assert_eq!(msg.version, Some(1));
+        assert_eq!(msg.timestamp.as_deref(), Some("2003-10-11T22:14:15.003Z"));
+        assert_eq!(msg.hostname.as_deref(), Some("mymachine.example.com"));
+        assert_eq!(msg.app_name.as_deref(), Some("evntslog"));
+        assert_eq!(msg.proc_id, None);
+        assert_eq!(msg.msg_id.as_deref(), Some("ID47"));
+        assert_eq!(
+            msg.structured_data.as_deref(),
+            Some(r#"[exampleSDID@32473 iut="3" eventSource="App\]lication"][x@1 a="b"]"#)
+        );
+        assert_eq!(msg.msg, "An application event");
+        assert!(matches!(msg.log_level(), Some(LogLevel::Info)));
+
+        let msg = parse_line("<34>1 - - su 12 - -");
+        assert_eq!(msg.severity, Some(2));
+        assert_eq!(msg.proc_id.as_deref(), Some("12"));
+        assert_eq!(msg.structured_data, None);
+        assert_eq!(msg.msg, "");
+        assert!(matches!(msg.log_level(), Some(LogLevel::Fatal)));
+    }
+
+    #[test]
+    fn test_rfc3164() {
+        let msg = parse_line("<34>Oct 11 22:14:15 mymachine su[123]: 'su root' failed");
+        assert_eq!(msg.facility_name(), Some("auth"));
+        assert_eq!(msg.severity_name(), Some("crit"));
+        assert_eq!(msg.version, None);
+        assert_eq!(msg.timestamp.as_deref(), Some("Oct 11 22:14:15"));
+        assert_eq!(msg.hostname.as_deref(), Some("mymachine"));
+        assert_eq!(msg.app_name.as_deref(), Some("su"));
+        assert_eq!(msg.proc_id.as_deref(), Some("123"));
+        assert_eq!(msg.msg, "'su root' failed");
+        assert_eq!(
+            msg.to_string().replace(COLUMN_SEP, "|"),
+            "|auth|crit|Oct 11 22:14:15|mymachine|su|123|||'su root' failed"
+        );
+
+        let msg = parse_line("<13>Feb  5 17:32:18 kernel: eth0 up");
+        assert_eq!(msg.hostname, None);
+        assert_eq!(msg.app_name.as_deref(), Some("kernel"));
+        assert_eq!(msg.msg, "eth0 up");
+        assert!(matches!(msg.log_level(), Some(LogLevel::Info)));
+    }
+
+    #[test]
+    fn test_sender() {
+        let msg = parse_line("@10.0.0.7:514 <13>Feb  5 17:32:18 kernel: eth0 up");
+        assert_eq!(msg.sender.as_deref(), Some("10.0.0.7:514"));
+        assert_eq!(msg.app_name.as_deref(), Some("kernel"));
+        let mut exported = vec![];
+        msg.to_writer(&mut exported).unwrap();
+        assert_eq!(exported, b"<13>Feb  5 17:32:18 kernel: eth0 up");
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        for line in ["no syslog", "<999>1 - - - - - -"] {
+            let msg = parse_line(line);
+            assert_eq!(msg, SyslogMessage::unparsed(line));
+            assert_eq!(msg.facility_name(), None);
+            assert!(msg.log_level().is_none());
+            assert_eq!(
+                msg.to_string().replace(COLUMN_SEP, "|"),
+                format!("|||||||||{line}")
+            );
+        }
+        let msg = parse_line("@10.0.0.7:514 no syslog");
+        assert_eq!(msg.sender.as_deref(), Some("10.0.0.7:514"));
+        assert_eq!(msg.msg, "no syslog");
+        assert_eq!(parse_line("<14>hello").msg, "hello");
+    }
+}
@@ -1,8 +1,127 @@
-use crate::{Error, LogMessage, ParseYield, Parser};
-use serde::Serialize;
+use crate::{Error, LogMessage, ParseYield, Parser, NEWLINE_SENTINAL};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{fmt, io::Write};
 
-pub struct StringTokenizer {}
+/// Encodings supported for plain text sources
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// detect the encoding from a byte order mark, fall back to UTF-8
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// detects the encoding by the byte order mark at the beginning of `input`.
+    /// Returns the encoding together with the length of the byte order mark.
+    pub fn from_bom(input: &[u8]) -> Option<(TextEncoding, usize)> {
+        match input {
+            [0xEF, 0xBB, 0xBF, ..] => Some((TextEncoding::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((TextEncoding::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((TextEncoding::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+
+    /// content in this encoding can be used as it is without decoding
+    pub fn is_utf8(&self) -> bool {
+        matches!(self, TextEncoding::Auto | TextEncoding::Utf8)
+    }
+
+    /// finds the end of the first line in `input`.
+    /// Returns the length of the line content and the length of the line terminator.
+    fn line_end(&self, input: &[u8]) -> Option<(usize, usize)> {
+        use memchr::memchr;
+        match self {
+            TextEncoding::Utf16Le => input
+                .chunks_exact(2)
+                .position(|unit| unit == [b'\n', 0x00])
+                .map(|pos| (pos * 2, 2)),
+            TextEncoding::Utf16Be => input
+                .chunks_exact(2)
+                .position(|unit| unit == [0x00, b'\n'])
+                .map(|pos| (pos * 2, 2)),
+            // in all other supported encodings the newline byte cannot be part
+            // of a multi-byte sequence
+            _ => memchr(b'\n', input).map(|pos| (pos, 1)),
+        }
+    }
+
+    fn decode(&self, input: &[u8]) -> String {
+        match self {
+            TextEncoding::Auto | TextEncoding::Utf8 => String::from_utf8_lossy(input).to_string(),
+            TextEncoding::Latin1 => input.iter().map(|b| *b as char).collect(),
+            TextEncoding::Utf16Le => encoding_rs::UTF_16LE
+                .decode_without_bom_handling(input)
+                .0
+                .to_string(),
+            TextEncoding::Utf16Be => encoding_rs::UTF_16BE
+                .decode_without_bom_handling(input)
+                .0
+                .to_string(),
+            TextEncoding::Windows1252 => encoding_rs::WINDOWS_1252
+                .decode_without_bom_handling(input)
+                .0
+                .to_string(),
+            TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS
+                .decode_without_bom_handling(input)
+                .0
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct StringTokenizer {
+    encoding: TextEncoding,
+    bom_checked: bool,
+    record_start: Option<Regex>,
+    record: Option<String>,
+}
+
+impl StringTokenizer {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            encoding,
+            ..Default::default()
+        }
+    }
+
+    /// group lines into records: a new record begins with each line matching
+    /// `record_start`, all other lines are continuation lines of the previous record.
+    /// A record is delivered as soon as the next record begins (or the source ends or
+    /// pauses).
+    pub fn with_record_start(mut self, record_start: &str) -> Result<Self, regex::Error> {
+        self.record_start = Some(Regex::new(record_start)?);
+        Ok(self)
+    }
+
+    /// adds `line` to the current record. Returns the previous record in case
+    /// `line` begins a new one.
+    fn group(&mut self, line: String) -> Option<StringMessage> {
+        let begins_record = match &self.record_start {
+            Some(record_start) => record_start.is_match(&line),
+            None => true,
+        };
+        match self.record.as_mut() {
+            Some(record) if !begins_record => {
+                record.push('\n');
+                record.push_str(&line);
+                None
+            }
+            _ => self
+                .record
+                .replace(line)
+                .map(|content| StringMessage { content }),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct StringMessage {
@@ -11,7 +130,15 @@ pub struct StringMessage {
 
 impl fmt::Display for StringMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.content)
+        if self.content.contains('\n') {
+            write!(
+                f,
+                "{}",
+                self.content.replace('\n', &NEWLINE_SENTINAL.to_string())
+            )
+        } else {
+            write!(f, "{}", self.content)
+        }
     }
 }
 
@@ -32,17 +159,40 @@ where
         input: &'b [u8],
         _timestamp: Option<u64>,
     ) -> Result<(&'b [u8], Option<ParseYield<StringMessage>>), Error> {
-        // TODO: support non-utf8 encodings
-        use memchr::memchr;
         if input.is_empty() {
             return Ok((input, None));
         }
-        if let Some(msg_size) = memchr(b'\n', input) {
-            let content = String::from_utf8_lossy(&input[..msg_size]);
+        let mut input = input;
+        if !self.bom_checked {
+            self.bom_checked = true;
+            match TextEncoding::from_bom(input) {
+                Some((detected, bom_len))
+                    if self.encoding == TextEncoding::Auto || self.encoding == detected =>
+                {
+                    self.encoding = detected;
+                    input = &input[bom_len..];
+                }
+                _ if self.encoding == TextEncoding::Auto => {
+                    self.encoding = TextEncoding::Utf8;
+                }
+                _ => {}
+            }
+        }
+        if self.record_start.is_some() {
+            let (line, rest) = match self.encoding.line_end(input) {
+                Some((msg_size, terminator_len)) => (
+                    self.encoding.decode(&input[..msg_size]),
+                    &input[msg_size + terminator_len..],
+                ),
+                None => (self.encoding.decode(input), &input[input.len()..]),
+            };
+            return Ok((rest, self.group(line).map(ParseYield::from)));
+        }
+        if let Some((msg_size, terminator_len)) = self.encoding.line_end(input) {
             let string_msg = StringMessage {
-                content: content.to_string(),
+                content: self.encoding.decode(&input[..msg_size]),
             };
-            Ok((&input[msg_size + 1..], Some(string_msg.into())))
+            Ok((&input[msg_size + terminator_len..], Some(string_msg.into())))
         } else {
             Ok((
                 &[],
@@ -52,11 +202,25 @@ where
             ))
         }
     }
+
+    fn finish(&mut self) -> Option<ParseYield<StringMessage>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Option<ParseYield<StringMessage>> {
+        self.record
+            .take()
+            .map(|content| ParseYield::from(StringMessage { content }))
+    }
+
+    fn has_pending(&self) -> bool {
+        self.record.is_some()
+    }
 }
 
 #[test]
 fn test_string_tokenizer() {
-    let mut parser = StringTokenizer {};
+    let mut parser = StringTokenizer::default();
     let content = b"hello\nworld\n";
     let (rest_1, first_msg) = parser.parse(content, None).unwrap();
     match first_msg {
@@ -73,3 +237,64 @@ fn test_string_tokenizer() {
     println!("rest_3 = {:?}", String::from_utf8_lossy(rest_3));
     assert!(third_msg.is_none());
 }
+
+#[test]
+fn test_string_tokenizer_utf16_bom() {
+    let mut parser = StringTokenizer::default();
+    // BOM + "hä\n" + "x\n" in UTF-16LE
+    let content: &[u8] = &[
+        0xFF, 0xFE, b'h', 0x00, 0xE4, 0x00, b'\n', 0x00, b'x', 0x00, b'\n', 0x00,
+    ];
+    let (rest_1, first_msg) = parser.parse(content, None).unwrap();
+    match first_msg {
+        Some(ParseYield::Message(StringMessage { content })) if content.eq("hä") => {}
+        _ => panic!("First message did not match"),
+    }
+    let (rest_2, second_msg) = parser.parse(rest_1, None).unwrap();
+    match second_msg {
+        Some(ParseYield::Message(StringMessage { content })) if content.eq("x") => {}
+        _ => panic!("Second message did not match"),
+    }
+    assert!(rest_2.is_empty());
+}
+
+#[test]
+fn test_string_tokenizer_latin1() {
+    let mut parser = StringTokenizer::new(TextEncoding::Latin1);
+    let (_, msg) = parser.parse(b"gr\xFC\xDFe\n", None).unwrap();
+    match msg {
+        Some(ParseYield::Message(StringMessage { content })) if content.eq("grüße") => {}
+        _ => panic!("Message did not match"),
+    }
+}
+
+#[test]
+fn test_string_tokenizer_records() {
+    let mut parser = StringTokenizer::default()
+        .with_record_start(r"^\d{2}:\d{2}")
+        .unwrap();
+    let mut input: &[u8] = b"10:00 start\n10:01 Exception\n  at a.b()\n  at c.d()\n10:02 done\n";
+    let mut records = vec![];
+    while !input.is_empty() {
+        let (rest, msg) = parser.parse(input, None).unwrap();
+        if let Some(ParseYield::Message(msg)) = msg {
+            records.push(msg.content);
+        }
+        input = rest;
+    }
+    if let Some(ParseYield::Message(msg)) = parser.finish() {
+        records.push(msg.content);
+    }
+    assert_eq!(
+        records,
+        vec![
+            "10:00 start",
+            "10:01 Exception\n  at a.b()\n  at c.d()",
+            "10:02 done"
+        ]
+    );
+    let grouped = StringMessage {
+        content: records[1].clone(),
+    };
+    assert!(!grouped.to_string().contains('\n'));
+}
@@ -8,19 +8,21 @@ use indexer_base::config::IndexSection;
 use log::debug;
 use parsers::{
     dlt::{fmt::FormatOptions, DltParser},
+    json::JsonLinesParser,
     someip::SomeipParser,
-    text::StringTokenizer,
+    syslog::SyslogParser,
     LogMessage, MessageStreamItem,
 };
 use processor::export::{export_raw, ExportError};
 use sources::{
     binary::{
+        compressed::DecompressingReader,
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
     },
     factory::{FileFormat, ParserType},
     producer::MessageProducer,
-    ByteSource,
+    ByteSource, SourceFilter,
 };
 use std::{
     fs::File,
@@ -50,7 +52,7 @@ pub async fn execute_export(
         .map(IndexSection::from)
         .collect::<Vec<IndexSection>>();
     let count = observed.get_files().len();
-    for (i, (parser, file_format, filename)) in observed.get_files().iter().enumerate() {
+    for (i, (parser, file_format, filename, filter)) in observed.get_files().iter().enumerate() {
         if indexes.is_empty() {
             break;
         }
@@ -59,6 +61,7 @@ pub async fn execute_export(
             &out_path,
             parser,
             file_format,
+            filter.as_ref(),
             &indexes,
             i != (count - 1),
             cancel,
@@ -78,26 +81,31 @@ pub async fn execute_export(
     Ok(Some(true))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn assing_source(
     src: &PathBuf,
     dest: &Path,
     parser: &ParserType,
     file_format: &FileFormat,
+    filter: Option<&SourceFilter>,
     sections: &Vec<IndexSection>,
     read_to_end: bool,
     cancel: &CancellationToken,
 ) -> Result<Option<usize>, NativeError> {
-    let reader = File::open(src).map_err(|e| NativeError {
-        severity: Severity::ERROR,
-        kind: NativeErrorKind::Io,
-        message: Some(format!("Fail open file {}: {}", src.to_string_lossy(), e)),
-    })?;
+    let reader = File::open(src)
+        .and_then(DecompressingReader::new)
+        .map_err(|e| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Io,
+            message: Some(format!("Fail open file {}: {}", src.to_string_lossy(), e)),
+        })?;
     match file_format {
         FileFormat::Binary | FileFormat::Text => {
             export(
                 dest,
                 parser,
                 BinaryByteSource::new(reader),
+                filter,
                 sections,
                 read_to_end,
                 cancel,
@@ -109,6 +117,7 @@ async fn assing_source(
                 dest,
                 parser,
                 PcapngByteSource::new(reader)?,
+                filter,
                 sections,
                 read_to_end,
                 cancel,
@@ -120,6 +129,7 @@ async fn assing_source(
                 dest,
                 parser,
                 PcapLegacyByteSource::new(reader)?,
+                filter,
                 sections,
                 read_to_end,
                 cancel,
@@ -133,6 +143,7 @@ async fn export<S: ByteSource>(
     dest: &Path,
     parser: &ParserType,
     source: S,
+    filter: Option<&SourceFilter>,
     sections: &Vec<IndexSection>,
     read_to_end: bool,
     cancel: &CancellationToken,
@@ -143,8 +154,10 @@ async fn export<S: ByteSource>(
                 SomeipParser::from_fibex_files(files.iter().map(PathBuf::from).collect())
             } else {
                 SomeipParser::new()
-            };
-            let mut producer = MessageProducer::new(parser, source, None);
+            }
+            .with_filter_config(settings.filter_config.as_ref().map(|f| f.into()));
+            let mut producer =
+                MessageProducer::new(parser, source, None).with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
@@ -162,8 +175,10 @@ async fn export<S: ByteSource>(
                 settings.fibex_metadata.as_ref(),
                 fmt_options.as_ref(),
                 settings.with_storage_header,
-            );
-            let mut producer = MessageProducer::new(parser, source, None);
+            )
+            .with_serial_header(settings.with_serial_header);
+            let mut producer =
+                MessageProducer::new(parser, source, None).with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
@@ -174,8 +189,51 @@ async fn export<S: ByteSource>(
             )
             .await
         }
-        ParserType::Text => {
-            let mut producer = MessageProducer::new(StringTokenizer {}, source, None);
+        ParserType::Text(settings) => {
+            let mut producer =
+                MessageProducer::new(super::observing::text_tokenizer(settings)?, source, None)
+                    .with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::JsonLines(settings) => {
+            let parser = JsonLinesParser::new(settings.columns.clone());
+            let mut producer =
+                MessageProducer::new(parser, source, None).with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::Regex(settings) => {
+            let mut producer =
+                MessageProducer::new(super::observing::regex_parser(settings)?, source, None)
+                    .with_filter(filter.cloned());
+            export_runner(
+                Box::pin(producer.as_stream()),
+                dest,
+                sections,
+                read_to_end,
+                true,
+                cancel,
+            )
+            .await
+        }
+        ParserType::Syslog => {
+            let mut producer = MessageProducer::new(SyslogParser::new(), source, None)
+                .with_filter(filter.cloned());
             export_runner(
                 Box::pin(producer.as_stream()),
                 dest,
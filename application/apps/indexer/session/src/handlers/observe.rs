@@ -7,7 +7,7 @@ use crate::{
 };
 use log::error;
 use sources::{
-    factory::{ObserveOptions, ObserveOrigin, ParserType},
+    factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType},
     producer::SdeReceiver,
 };
 
@@ -26,7 +26,7 @@ pub async fn start_observing(
     match &options.origin {
         ObserveOrigin::File(uuid, file_origin, filename) => {
             let (is_text, session_file_origin) = (
-                matches!(options.parser, ParserType::Text),
+                matches!(options.parser, ParserType::Text(_)),
                 state.get_session_file_origin().await?,
             );
             match session_file_origin {
@@ -45,6 +45,21 @@ pub async fn start_observing(
                         state,
                         &[(uuid.clone(), file_origin.clone(), filename.clone())],
                         &options.parser,
+                        options.filter.as_ref(),
+                    )
+                    .await
+                }
+                _ if options.split_connections
+                    && matches!(file_origin, FileFormat::PcapNG | FileFormat::PcapLegacy) =>
+                {
+                    observing::connections::observe_connections(
+                        operation_api,
+                        state,
+                        uuid,
+                        file_origin,
+                        filename,
+                        &options.parser,
+                        options.filter.as_ref(),
                     )
                     .await
                 }
@@ -56,6 +71,7 @@ pub async fn start_observing(
                         file_origin,
                         filename,
                         &options.parser,
+                        options.filter.as_ref(),
                     )
                     .await
                 }
@@ -69,9 +85,53 @@ pub async fn start_observing(
                     message: Some(String::from("No files are defined for Concat operation")),
                 })
             } else {
-                observing::concat::concat_files(operation_api, state, files, &options.parser).await
+                observing::concat::concat_files(
+                    operation_api,
+                    state,
+                    files,
+                    &options.parser,
+                    options.filter.as_ref(),
+                )
+                .await
+            }
+        }
+        ObserveOrigin::Archive(archive, members) => {
+            if members.is_empty() {
+                Err(NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Configuration,
+                    message: Some(String::from("No files of the archive are defined")),
+                })
+            } else {
+                observing::concat::concat_archive_members(
+                    operation_api,
+                    state,
+                    archive,
+                    members,
+                    &options.parser,
+                    options.filter.as_ref(),
+                )
+                .await
             }
         }
+        ObserveOrigin::Directory {
+            path,
+            glob,
+            format,
+            order,
+        } => {
+            observing::directory::observe_directory(
+                operation_api,
+                state,
+                path,
+                glob,
+                format,
+                order,
+                &options.parser,
+                options.filter.as_ref(),
+            )
+            .await
+        }
         ObserveOrigin::Stream(uuid, transport) => {
             observing::stream::observe_stream(
                 operation_api,
@@ -79,6 +139,7 @@ pub async fn start_observing(
                 uuid,
                 transport,
                 &options.parser,
+                options.filter.as_ref(),
                 rx_sde,
             )
             .await
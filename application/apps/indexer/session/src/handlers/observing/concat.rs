@@ -3,15 +3,23 @@ use crate::{
     operations::{OperationAPI, OperationResult},
     progress::Severity,
     state::SessionStateAPI,
+    tail,
 };
 use sources::{
     binary::{
+        archive::Archive,
+        compressed::DecompressingReader,
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
     },
     factory::{FileFormat, ParserType},
+    SourceFilter,
 };
-use std::{fs::File, path::PathBuf};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+use tokio::sync::mpsc::Receiver;
 
 #[allow(clippy::type_complexity)]
 pub async fn concat_files(
@@ -19,6 +27,7 @@ pub async fn concat_files(
     state: SessionStateAPI,
     files: &[(String, FileFormat, PathBuf)],
     parser: &ParserType,
+    filter: Option<&SourceFilter>,
 ) -> OperationResult<()> {
     for file in files.iter() {
         let (uuid, _file_type, _filename) = file;
@@ -26,74 +35,148 @@ pub async fn concat_files(
     }
     for file in files.iter() {
         let (uuid, file_type, filename) = file;
-        let source_id = state.get_source(uuid).await?.ok_or(NativeError {
-            severity: Severity::ERROR,
-            kind: NativeErrorKind::Io,
-            message: Some(format!(
-                "Cannot find source id for file {} with alias {}",
-                filename.to_string_lossy(),
-                uuid,
-            )),
-        })?;
-        let input_file = File::open(filename).map_err(|e| NativeError {
-            severity: Severity::ERROR,
-            kind: NativeErrorKind::Io,
-            message: Some(format!(
-                "Fail open file {}: {}",
-                filename.to_string_lossy(),
-                e
-            )),
-        })?;
-        match file_type {
-            FileFormat::Binary => {
-                super::run_source(
-                    operation_api.clone(),
-                    state.clone(),
-                    BinaryByteSource::new(input_file),
-                    source_id,
-                    parser,
-                    None,
-                    None,
-                )
-                .await?
-            }
-            FileFormat::PcapLegacy => {
-                super::run_source(
-                    operation_api.clone(),
-                    state.clone(),
-                    PcapLegacyByteSource::new(input_file)?,
-                    source_id,
-                    parser,
-                    None,
-                    None,
-                )
-                .await?
-            }
-            FileFormat::PcapNG => {
-                super::run_source(
-                    operation_api.clone(),
-                    state.clone(),
-                    PcapngByteSource::new(input_file)?,
-                    source_id,
-                    parser,
-                    None,
-                    None,
-                )
-                .await?
-            }
-            FileFormat::Text => {
-                super::run_source(
-                    operation_api.clone(),
-                    state.clone(),
-                    BinaryByteSource::new(input_file),
-                    source_id,
-                    parser,
-                    None,
-                    None,
-                )
-                .await?
-            }
-        };
+        let source_id = source_id(&state, uuid, &filename.to_string_lossy()).await?;
+        let (input, progress) = super::open_input(filename)?;
+        super::with_read_progress(
+            &operation_api,
+            progress,
+            run_input(
+                operation_api.clone(),
+                state.clone(),
+                input,
+                file_type,
+                source_id,
+                parser,
+                filter,
+                None,
+            ),
+        )
+        .await?;
+    }
+    Ok(Some(()))
+}
+
+/// reads the given files of a zip or tar archive one after another, without extracting them.
+/// The files are read in the order of the archive, so that it's read in one pass.
+pub async fn concat_archive_members(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    path: &Path,
+    members: &[(String, FileFormat, String)],
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    for (uuid, _file_type, _member) in members.iter() {
+        state.add_source(uuid).await?;
+    }
+    let open_error = |e: String| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Io,
+        message: Some(format!(
+            "Fail to open archive {}: {e}",
+            path.to_string_lossy()
+        )),
+    };
+    // listing a compressed tar archive means decompressing all of it
+    let archive_path = path.to_owned();
+    let archive = tokio::task::spawn_blocking(move || Archive::open(&archive_path))
+        .await
+        .map_err(|e| open_error(e.to_string()))?
+        .map_err(|e| open_error(e.to_string()))?;
+    let mut members: Vec<&(String, FileFormat, String)> = members.iter().collect();
+    members.sort_by_key(|(_, _, member)| archive.index_of(member));
+    for (uuid, file_type, member) in members {
+        let source_id = source_id(&state, uuid, member).await?;
+        // members can be compressed on their own, like a .dlt.gz in a zip archive
+        let input = archive
+            .reader(member)
+            .map_err(|e| e.to_string())
+            .and_then(|reader| DecompressingReader::new(reader).map_err(|e| e.to_string()))
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Io,
+                message: Some(format!(
+                    "Fail open {} in archive {}: {}",
+                    member,
+                    path.to_string_lossy(),
+                    e
+                )),
+            })?;
+        run_input(
+            operation_api.clone(),
+            state.clone(),
+            input,
+            file_type,
+            source_id,
+            parser,
+            filter,
+            None,
+        )
+        .await?;
     }
     Ok(Some(()))
 }
+
+async fn source_id(state: &SessionStateAPI, uuid: &str, name: &str) -> Result<u16, NativeError> {
+    state.get_source(uuid).await?.ok_or(NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Io,
+        message: Some(format!(
+            "Cannot find source id for file {name} with alias {uuid}",
+        )),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run_input<R: Read + Send + Sync + Unpin>(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    input: R,
+    file_type: &FileFormat,
+    source_id: u16,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+    rx_tail: Option<Receiver<Result<(), tail::Error>>>,
+) -> OperationResult<()> {
+    match file_type {
+        FileFormat::Binary | FileFormat::Text => {
+            super::run_source(
+                operation_api,
+                state,
+                BinaryByteSource::new(input),
+                source_id,
+                parser,
+                filter,
+                None,
+                rx_tail,
+            )
+            .await
+        }
+        FileFormat::PcapLegacy => {
+            super::run_source(
+                operation_api,
+                state,
+                PcapLegacyByteSource::new(input)?,
+                source_id,
+                parser,
+                filter,
+                None,
+                rx_tail,
+            )
+            .await
+        }
+        FileFormat::PcapNG => {
+            super::run_source(
+                operation_api,
+                state,
+                PcapngByteSource::new(input)?,
+                source_id,
+                parser,
+                filter,
+                None,
+                rx_tail,
+            )
+            .await
+        }
+    }
+}
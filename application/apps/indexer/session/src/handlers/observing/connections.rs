@@ -0,0 +1,120 @@
+use crate::{
+    events::NativeError,
+    operations::{OperationAPI, OperationResult},
+    state::SessionStateAPI,
+};
+use log::warn;
+use sources::{
+    binary::pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource, reassembly::Connection},
+    factory::{FileFormat, ParserType},
+    ByteSource, SourceFilter, TransportProtocol,
+};
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+
+/// Observes each TCP connection of a network trace as an own source. The file is
+/// read once to find the connections, which are observed one after another then.
+pub async fn observe_connections(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    uuid: &str,
+    file_format: &FileFormat,
+    filename: &Path,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    let cancel = operation_api.cancellation_token();
+    let connections = tcp_connections(file_format, filename, filter, &cancel).await?;
+    if cancel.is_cancelled() {
+        return Ok(None);
+    }
+    if connections.is_empty() {
+        warn!(
+            "No TCP connections found in {}, observed as one source",
+            filename.display()
+        );
+        let source_id = state.add_source(uuid).await?;
+        let (input, progress) = super::open_input(filename)?;
+        return super::with_read_progress(
+            &operation_api,
+            progress,
+            super::concat::run_input(
+                operation_api.clone(),
+                state,
+                input,
+                file_format,
+                source_id,
+                parser,
+                filter,
+                None,
+            ),
+        )
+        .await;
+    }
+    for connection in connections {
+        let source_id = state.add_source(&format!("{uuid}:{connection}")).await?;
+        let connection_filter = SourceFilter {
+            transport: Some(TransportProtocol::TCP),
+            source_ip: Some(connection.source.ip()),
+            source_port: Some(connection.source.port()),
+            destination_ip: Some(connection.destination.ip()),
+            destination_port: Some(connection.destination.port()),
+            ..filter.cloned().unwrap_or_default()
+        };
+        let (input, progress) = super::open_input(filename)?;
+        super::with_read_progress(
+            &operation_api,
+            progress,
+            super::concat::run_input(
+                operation_api.clone(),
+                state.clone(),
+                input,
+                file_format,
+                source_id,
+                parser,
+                Some(&connection_filter),
+                None,
+            ),
+        )
+        .await?;
+    }
+    Ok(Some(()))
+}
+
+/// TCP connections of the file, which carry data and match the filter
+async fn tcp_connections(
+    file_format: &FileFormat,
+    filename: &Path,
+    filter: Option<&SourceFilter>,
+    cancel: &CancellationToken,
+) -> Result<Vec<Connection>, NativeError> {
+    let filter = SourceFilter {
+        transport: Some(TransportProtocol::TCP),
+        ..filter.cloned().unwrap_or_default()
+    };
+    let (input, _) = super::open_input(filename)?;
+    Ok(match file_format {
+        FileFormat::PcapNG => {
+            let mut source = PcapngByteSource::new(input)?;
+            read_to_end(&mut source, &filter, cancel).await?;
+            source.connections().to_vec()
+        }
+        FileFormat::PcapLegacy => {
+            let mut source = PcapLegacyByteSource::new(input)?;
+            read_to_end(&mut source, &filter, cancel).await?;
+            source.connections().to_vec()
+        }
+        FileFormat::Text | FileFormat::Binary => vec![],
+    })
+}
+
+async fn read_to_end<S: ByteSource>(
+    source: &mut S,
+    filter: &SourceFilter,
+    cancel: &CancellationToken,
+) -> Result<(), NativeError> {
+    while !cancel.is_cancelled() && source.reload(Some(filter)).await?.is_some() {
+        source.consume(source.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::{
+    events::{NativeError, NativeErrorKind},
+    operations::{OperationAPI, OperationResult},
+    progress::Severity,
+    state::SessionStateAPI,
+    tail::{self, FileWatcher},
+};
+use glob::Pattern;
+use log::warn;
+use sources::{
+    factory::{FileFormat, FileOrder, ParserType},
+    SourceFilter,
+};
+use std::{
+    collections::HashSet,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    join, select,
+    sync::mpsc::{channel, Sender},
+};
+use tokio_util::sync::CancellationToken;
+
+/// files of the directory, which are observed already
+type Observed = HashSet<PathBuf>;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn observe_directory(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    path: &Path,
+    glob: &str,
+    format: &FileFormat,
+    order: &FileOrder,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    let pattern = Pattern::new(glob).map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Configuration,
+        message: Some(format!("Invalid pattern {glob}: {e}")),
+    })?;
+    let watcher = FileWatcher::directory(path)
+        .map_err(|err| {
+            warn!(
+                "Fail to watch {}, new files are polled instead: {err}",
+                path.display()
+            );
+        })
+        .ok();
+    let cancel = operation_api.cancellation_token();
+    let mut observed = Observed::new();
+    let mut pending = matching_files(path, &pattern, order, &observed)?;
+    operation_api.processing();
+    while !cancel.is_cancelled() {
+        if pending.is_empty() {
+            select! {
+                _ = cancel.cancelled() => break,
+                _ = tail::next_check(watcher.as_ref()) => {},
+            };
+            pending = matching_files(path, &pattern, order, &observed)?;
+            continue;
+        }
+        let filename = pending.remove(0);
+        observed.insert(filename.clone());
+        let (input, progress) = match super::open_input(&filename) {
+            Ok(opened) => opened,
+            // the file was removed (e.g. rotated away) since it was listed
+            Err(err) if !filename.exists() => {
+                warn!(
+                    "Skip {}, which is gone: {:?}",
+                    filename.display(),
+                    err.message
+                );
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        // each file is an own source, the path is used as alias
+        let source_id = state.add_source(&filename.to_string_lossy()).await?;
+        if !pending.is_empty() {
+            // there are newer files already, this one is just read to its end
+            super::with_read_progress(
+                &operation_api,
+                progress,
+                super::concat::run_input(
+                    operation_api.clone(),
+                    state.clone(),
+                    input,
+                    format,
+                    source_id,
+                    parser,
+                    filter,
+                    None,
+                ),
+            )
+            .await?;
+            continue;
+        }
+        let (tx_tail, rx_tail) = channel(1);
+        let (result, newer) = join!(
+            super::with_read_progress(
+                &operation_api,
+                progress,
+                super::concat::run_input(
+                    operation_api.clone(),
+                    state.clone(),
+                    input,
+                    format,
+                    source_id,
+                    parser,
+                    filter,
+                    Some(rx_tail),
+                ),
+            ),
+            follow(
+                &filename,
+                Directory {
+                    path,
+                    pattern: &pattern,
+                    order,
+                    observed: &observed,
+                },
+                watcher.as_ref(),
+                tx_tail,
+                &cancel,
+            ),
+        );
+        result?;
+        pending = newer?;
+    }
+    Ok(None)
+}
+
+struct Directory<'a> {
+    path: &'a Path,
+    pattern: &'a Pattern,
+    order: &'a FileOrder,
+    observed: &'a Observed,
+}
+
+/// Keeps the file, which is read currently, followed until newer files appear in
+/// the directory, which are returned. Once it returns, the file is read to its
+/// end and the reading is done.
+async fn follow(
+    filename: &Path,
+    directory: Directory<'_>,
+    watcher: Option<&FileWatcher>,
+    tx_tail: Sender<Result<(), tail::Error>>,
+    cancel: &CancellationToken,
+) -> Result<Vec<PathBuf>, NativeError> {
+    let mut size = std::fs::metadata(filename)?.len();
+    loop {
+        select! {
+            _ = cancel.cancelled() => return Ok(vec![]),
+            // reading is done already (e.g. because of an error)
+            _ = tx_tail.closed() => return Ok(vec![]),
+            _ = tail::next_check(watcher) => {},
+        };
+        let newer = matching_files(
+            directory.path,
+            directory.pattern,
+            directory.order,
+            directory.observed,
+        )?;
+        if !newer.is_empty() {
+            // bytes appended since the last check are read before the file is left
+            let _ = tx_tail.send(Ok(())).await;
+            return Ok(newer);
+        }
+        let updated = std::fs::metadata(filename)
+            .map(|md| md.len())
+            .unwrap_or(size);
+        if updated != size {
+            size = updated;
+            if tx_tail.send(Ok(())).await.is_err() {
+                return Ok(vec![]);
+            }
+        }
+    }
+}
+
+/// files of the directory matching the pattern, which aren't observed yet, in the
+/// order they should be observed
+fn matching_files(
+    path: &Path,
+    pattern: &Pattern,
+    order: &FileOrder,
+    observed: &Observed,
+) -> Result<Vec<PathBuf>, NativeError> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path).map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Io,
+        message: Some(format!("Fail to read directory {}: {e}", path.display())),
+    })? {
+        let entry = entry?;
+        let filename = entry.path();
+        if observed.contains(&filename) || !pattern.matches(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        // files might be removed meanwhile, e.g. the oldest ones by a rotation
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        files.push((filename, metadata.modified()?));
+    }
+    match order {
+        FileOrder::Name => files.sort_by(|(a, _), (b, _)| a.cmp(b)),
+        FileOrder::Modified => files.sort_by(|(a, a_modified), (b, b_modified)| {
+            a_modified.cmp(b_modified).then_with(|| a.cmp(b))
+        }),
+    }
+    Ok(files.into_iter().map(|(filename, _)| filename).collect())
+}
@@ -5,14 +5,22 @@ use crate::{
     state::SessionStateAPI,
     tail,
 };
+use parsers::text::TextEncoding;
 use sources::{
     binary::{
+        compressed::Compression,
         pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
         raw::BinaryByteSource,
+        rotating::{rotation, RotatingFile, Rotation},
     },
     factory::{FileFormat, ParserType},
+    SourceFilter,
+};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
 };
-use std::{fs::File, path::Path};
 use tokio::{
     join, select,
     sync::mpsc::{channel, Receiver, Sender},
@@ -26,62 +34,92 @@ pub async fn observe_file<'a>(
     file_format: &FileFormat,
     filename: &Path,
     parser: &'a ParserType,
+    filter: Option<&SourceFilter>,
 ) -> OperationResult<()> {
     let source_id = state.add_source(uuid).await?;
     let (tx_tail, mut rx_tail): (
         Sender<Result<(), tail::Error>>,
         Receiver<Result<(), tail::Error>>,
     ) = channel(1);
+    // text files, which are compressed, not encoded in UTF-8, whose lines are grouped
+    // into records or which are structured (JSON lines, regex captures, syslog) cannot
+    // be linked with the session and have to be parsed like any other binary source
+    let file_format =
+        if matches!(file_format, FileFormat::Text) && requires_parsing(parser, filename)? {
+            &FileFormat::Binary
+        } else {
+            file_format
+        };
     match file_format {
         FileFormat::Binary => {
-            let source = BinaryByteSource::new(input_file(filename)?);
+            let (input, progress) = super::open_tailed_input(filename)?;
+            let source = BinaryByteSource::new(input);
             let (_, listening) = join!(
                 tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
-                    operation_api,
-                    state,
-                    source,
-                    source_id,
-                    parser,
-                    None,
-                    Some(rx_tail)
+                super::with_read_progress(
+                    &operation_api,
+                    progress,
+                    super::run_source(
+                        operation_api.clone(),
+                        state,
+                        source,
+                        source_id,
+                        parser,
+                        filter,
+                        None,
+                        Some(rx_tail)
+                    )
                 )
             );
             listening
         }
         FileFormat::PcapLegacy => {
-            let source = PcapLegacyByteSource::new(input_file(filename)?)?;
+            let (input, progress) = super::open_tailed_input(filename)?;
+            let source = PcapLegacyByteSource::new(input)?;
             let (_, listening) = join!(
                 tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
-                    operation_api,
-                    state,
-                    source,
-                    source_id,
-                    parser,
-                    None,
-                    Some(rx_tail)
+                super::with_read_progress(
+                    &operation_api,
+                    progress,
+                    super::run_source(
+                        operation_api.clone(),
+                        state,
+                        source,
+                        source_id,
+                        parser,
+                        filter,
+                        None,
+                        Some(rx_tail)
+                    )
                 )
             );
             listening
         }
         FileFormat::PcapNG => {
-            let source = PcapngByteSource::new(input_file(filename)?)?;
+            let (input, progress) = super::open_tailed_input(filename)?;
+            let source = PcapngByteSource::new(input)?;
             let (_, listening) = join!(
                 tail::track(filename, tx_tail, operation_api.cancellation_token()),
-                super::run_source(
-                    operation_api,
-                    state,
-                    source,
-                    source_id,
-                    parser,
-                    None,
-                    Some(rx_tail)
+                super::with_read_progress(
+                    &operation_api,
+                    progress,
+                    super::run_source(
+                        operation_api.clone(),
+                        state,
+                        source,
+                        source_id,
+                        parser,
+                        filter,
+                        None,
+                        Some(rx_tail)
+                    )
                 )
             );
             listening
         }
         FileFormat::Text => {
+            // kept open to get the content read so far, if the file is rotated
+            let mut linked = input_file(filename)?;
             state.set_session_file(Some(filename.to_path_buf())).await?;
             // Grab main file content
             state.update_session(source_id).await?;
@@ -92,37 +130,86 @@ pub async fn observe_file<'a>(
             let cancel = operation_api.cancellation_token();
             let (result, tracker) = join!(
                 async {
-                    let result = select! {
-                        res = async move {
+                    let rotated = select! {
+                        res = async {
                             while let Some(update) = rx_tail.recv().await {
                                 update.map_err(|err| NativeError {
                                     severity: Severity::ERROR,
                                     kind: NativeErrorKind::Interrupted,
                                     message: Some(err.to_string()),
                                 })?;
+                                let (_, read_bytes) = state.get_stream_len().await?;
+                                if let Some(rotated) = rotation(&linked, filename, read_bytes)? {
+                                    return Ok::<Option<Rotation>, NativeError>(Some(rotated));
+                                }
                                 state.update_session(source_id).await?;
                             }
-                            Ok(())
-                        } => res,
-                        _ = cancel.cancelled() => Ok(())
+                            Ok(None)
+                        } => res?,
+                        _ = cancel.cancelled() => None
+                    };
+                    // rows of the session cannot be taken from the file anymore: the content
+                    // read so far is copied (if it's still available) and the rest is parsed
+                    // like any other source
+                    let position = match rotated {
+                        Some(Rotation::Rotated) => {
+                            state.unlink_session_file(Some(linked.try_clone()?)).await?
+                        }
+                        Some(Rotation::Truncated) => state.unlink_session_file(None).await?,
+                        None => return Ok(None),
                     };
-                    result
+                    linked.seek(SeekFrom::Start(position))?;
+                    super::run_source(
+                        operation_api.clone(),
+                        state,
+                        BinaryByteSource::new(RotatingFile::with_file(filename, linked)?),
+                        source_id,
+                        parser,
+                        filter,
+                        None,
+                        Some(rx_tail),
+                    )
+                    .await
                 },
                 tail::track(filename, tx_tail, operation_api.cancellation_token()),
             );
-            result
-                .and_then(|_| {
-                    tracker.map_err(|e| NativeError {
+            result.and_then(|result| {
+                tracker
+                    .map_err(|e| NativeError {
                         severity: Severity::ERROR,
                         kind: NativeErrorKind::Interrupted,
                         message: Some(format!("Tailing error: {e}")),
                     })
-                })
-                .map(|_| None)
+                    .map(|_| result)
+            })
         }
     }
 }
 
+fn requires_parsing(parser: &ParserType, filename: &Path) -> Result<bool, NativeError> {
+    if Compression::of_file(filename)?.is_some() {
+        return Ok(true);
+    }
+    let encoding = match parser {
+        ParserType::Text(Some(settings)) if settings.record_start.is_some() => return Ok(true),
+        ParserType::Text(settings) => settings
+            .as_ref()
+            .map(|settings| settings.encoding)
+            .unwrap_or_default(),
+        ParserType::JsonLines(_) | ParserType::Regex(_) | ParserType::Syslog => return Ok(true),
+        _ => return Ok(false),
+    };
+    if encoding != TextEncoding::Auto {
+        return Ok(!encoding.is_utf8());
+    }
+    let mut head = Vec::with_capacity(3);
+    input_file(filename)?.take(3).read_to_end(&mut head)?;
+    Ok(matches!(
+        TextEncoding::from_bom(&head),
+        Some((detected, _)) if !detected.is_utf8()
+    ))
+}
+
 fn input_file(filename: &Path) -> Result<File, NativeError> {
     File::open(filename).map_err(|e| NativeError {
         severity: Severity::ERROR,
@@ -134,3 +221,93 @@ fn input_file(filename: &Path) -> Result<File, NativeError> {
         )),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{session::Session, state::TRUNCATED_ROW};
+    use processor::grabber::LineRange;
+    use sources::factory::{FileFormat, ObserveOptions, ParserType};
+    use std::{fs::OpenOptions, io::Write, path::Path};
+    use tokio::time::{sleep, Duration};
+    use uuid::Uuid;
+
+    /// waits until the session consists of the expected rows
+    async fn wait_for_rows(session: &Session, expected: &[&str]) {
+        let mut rows = vec![];
+        for _ in 0..100 {
+            let len = session.get_stream_len().await.unwrap();
+            if len > 0 {
+                rows = session
+                    .grab(LineRange::from(0..=(len as u64 - 1)))
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|row| row.content)
+                    .collect::<Vec<String>>();
+                if rows == expected {
+                    return;
+                }
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("expected rows {expected:?}, got {rows:?}");
+    }
+
+    fn append(path: &Path, content: &[u8]) {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(content)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_observe_truncated_file() {
+        let path = std::env::temp_dir().join(format!("{}.log", Uuid::new_v4()));
+        std::fs::write(&path, b"first\nsecond\n").unwrap();
+        let (session, _rx_events) = Session::new(Uuid::new_v4()).await.unwrap();
+        session
+            .observe(
+                Uuid::new_v4(),
+                ObserveOptions::file(path.clone(), FileFormat::Text, ParserType::Text(None)),
+            )
+            .unwrap();
+        wait_for_rows(&session, &["first", "second"]).await;
+        // like logrotate with copytruncate
+        std::fs::write(&path, b"").unwrap();
+        append(&path, b"third\n");
+        wait_for_rows(&session, &[TRUNCATED_ROW, TRUNCATED_ROW, "third"]).await;
+        append(&path, b"fourth\n");
+        wait_for_rows(&session, &[TRUNCATED_ROW, TRUNCATED_ROW, "third", "fourth"]).await;
+        session.stop(Uuid::new_v4()).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_observe_rotated_file() {
+        let path = std::env::temp_dir().join(format!("{}.log", Uuid::new_v4()));
+        let rotated = path.with_extension("log.1");
+        std::fs::write(&path, b"first\n").unwrap();
+        let (session, _rx_events) = Session::new(Uuid::new_v4()).await.unwrap();
+        session
+            .observe(
+                Uuid::new_v4(),
+                ObserveOptions::file(path.clone(), FileFormat::Text, ParserType::Text(None)),
+            )
+            .unwrap();
+        wait_for_rows(&session, &["first"]).await;
+        // like logrotate with create
+        std::fs::rename(&path, &rotated).unwrap();
+        std::fs::write(&path, b"second\n").unwrap();
+        wait_for_rows(&session, &["first", "second"]).await;
+        append(&path, b"third\n");
+        wait_for_rows(&session, &["first", "second", "third"]).await;
+        append(&path, b"fourth\n");
+        wait_for_rows(&session, &["first", "second", "third", "fourth"]).await;
+        session.stop(Uuid::new_v4()).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -1,21 +1,38 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    fs::File,
+    future::Future,
+    io::Read,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use crate::{
+    events::{CallbackEvent, NativeError, NativeErrorKind},
     operations::{OperationAPI, OperationResult},
+    progress::{ProgressProviderAPI, Severity, Ticks},
     state::SessionStateAPI,
     tail,
 };
-use log::trace;
+use log::{trace, warn};
 use parsers::{
     dlt::{fmt::FormatOptions, DltParser},
+    json::JsonLinesParser,
+    pattern::RegexParser,
     someip::SomeipParser,
+    syslog::SyslogParser,
     text::StringTokenizer,
     LogMessage, MessageStreamItem, ParseYield, Parser,
 };
 use sources::{
-    factory::ParserType,
+    binary::{
+        compressed::{DecompressingReader, ReadPosition},
+        rotating::RotatingFile,
+    },
+    factory::{ParserType, RegexParserSettings, TextParserSettings},
     producer::{MessageProducer, SdeReceiver},
-    ByteSource,
+    ByteSource, SourceFilter,
 };
 use tokio::{
     select,
@@ -25,23 +42,112 @@ use tokio::{
 use tokio_stream::StreamExt;
 
 enum Next<T: LogMessage> {
-    Item(MessageStreamItem<T>),
+    /// item and the origin of its bytes, if the source provides it
+    Item(MessageStreamItem<T>, Option<String>),
     Timeout,
     Waiting,
 }
 
 pub mod concat;
+pub mod connections;
+pub mod directory;
 pub mod file;
 pub mod stream;
 
 pub const FLUSH_TIMEOUT_IN_MS: u128 = 500;
+/// how often the progress of reading a compressed file is reported
+const READ_PROGRESS_INTERVAL_IN_MS: u64 = 500;
+
+/// progress of reading a compressed file, counted in compressed bytes
+pub(crate) struct ReadProgress {
+    position: ReadPosition,
+    total: u64,
+}
+
+/// opens a file for reading, compressed files (gzip, zstd, xz, bzip2) are
+/// decompressed on the fly. For those the progress of reading is provided as well.
+pub(crate) fn open_input(
+    filename: &Path,
+) -> Result<(DecompressingReader<File>, Option<ReadProgress>), NativeError> {
+    decompressed_input(filename, File::open)
+}
+
+/// like `open_input`, but the file is followed if it's rotated or truncated
+/// while it's read, which is used for tailing
+pub(crate) fn open_tailed_input(
+    filename: &Path,
+) -> Result<(DecompressingReader<RotatingFile>, Option<ReadProgress>), NativeError> {
+    decompressed_input(filename, RotatingFile::open)
+}
+
+fn decompressed_input<R: Read, F: FnOnce(&Path) -> std::io::Result<R>>(
+    filename: &Path,
+    open: F,
+) -> Result<(DecompressingReader<R>, Option<ReadProgress>), NativeError> {
+    let io_error = |e: std::io::Error| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Io,
+        message: Some(format!(
+            "Fail open file {}: {}",
+            filename.to_string_lossy(),
+            e
+        )),
+    };
+    let input = open(filename).map_err(io_error)?;
+    let total = std::fs::metadata(filename).map_err(io_error)?.len();
+    let reader = DecompressingReader::new(input).map_err(io_error)?;
+    let progress = reader.compression().map(|_| ReadProgress {
+        position: reader.position(),
+        total,
+    });
+    Ok((reader, progress))
+}
+
+/// runs the operation and reports the progress of reading a compressed file meanwhile
+pub(crate) async fn with_read_progress<T, F: Future<Output = T>>(
+    operation_api: &OperationAPI,
+    progress: Option<ReadProgress>,
+    operation: F,
+) -> T {
+    let (progress, provider) = match (progress, ProgressProviderAPI::new()) {
+        (Some(progress), Ok(provider)) => (progress, provider),
+        (None, _) => return operation.await,
+        (Some(_), Err(e)) => {
+            warn!("Fail to report progress of reading: {e:?}");
+            return operation.await;
+        }
+    };
+    let report = || {
+        provider.progress(
+            &operation_api.id(),
+            Ticks {
+                count: progress.position.get(),
+                state: None,
+                total: Some(progress.total),
+            },
+        )
+    };
+    futures::pin_mut!(operation);
+    let mut interval = tokio::time::interval(Duration::from_millis(READ_PROGRESS_INTERVAL_IN_MS));
+    loop {
+        select! {
+            done = &mut operation => {
+                report();
+                return done;
+            }
+            _ = interval.tick() => report(),
+        }
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_source<S: ByteSource>(
     operation_api: OperationAPI,
     state: SessionStateAPI,
     source: S,
     source_id: u16,
     parser: &ParserType,
+    filter: Option<&SourceFilter>,
     rx_sde: Option<SdeReceiver>,
     rx_tail: Option<Receiver<Result<(), tail::Error>>>,
 ) -> OperationResult<()> {
@@ -52,48 +158,189 @@ pub async fn run_source<S: ByteSource>(
                     SomeipParser::from_fibex_files(paths.iter().map(PathBuf::from).collect())
                 }
                 None => SomeipParser::new(),
-            };
-            let producer = MessageProducer::new(someip_parser, source, rx_sde);
+            }
+            .with_filter_config(settings.filter_config.as_ref().map(|f| f.into()));
+            let producer =
+                MessageProducer::new(someip_parser, source, rx_sde).with_filter(filter.cloned());
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::Text(settings) => {
+            let producer = MessageProducer::new(text_tokenizer(settings)?, source, rx_sde)
+                .with_filter(filter.cloned());
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
-        ParserType::Text => {
-            let producer = MessageProducer::new(StringTokenizer {}, source, rx_sde);
+        ParserType::JsonLines(settings) => {
+            let json_parser = JsonLinesParser::new(settings.columns.clone());
+            let producer =
+                MessageProducer::new(json_parser, source, rx_sde).with_filter(filter.cloned());
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::Regex(settings) => {
+            let producer = MessageProducer::new(regex_parser(settings)?, source, rx_sde)
+                .with_filter(filter.cloned());
+            run_producer(operation_api, state, source_id, producer, rx_tail).await
+        }
+        ParserType::Syslog => {
+            let producer = MessageProducer::new(SyslogParser::new(), source, rx_sde)
+                .with_filter(filter.cloned());
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
         ParserType::Dlt(settings) => {
             let fmt_options = Some(FormatOptions::from(settings.tz.as_ref()));
+            let someip_parser = settings.someip_fibex_file_paths.as_ref().map(|paths| {
+                SomeipParser::from_fibex_files(paths.iter().map(PathBuf::from).collect())
+            });
             let dlt_parser = DltParser::new(
                 settings.filter_config.as_ref().map(|f| f.into()),
                 settings.fibex_metadata.as_ref(),
                 fmt_options.as_ref(),
                 settings.with_storage_header,
-            );
-            let producer = MessageProducer::new(dlt_parser, source, rx_sde);
+            )
+            .with_serial_header(settings.with_serial_header)
+            .with_someip_parser(someip_parser.as_ref());
+            let producer =
+                MessageProducer::new(dlt_parser, source, rx_sde).with_filter(filter.cloned());
             run_producer(operation_api, state, source_id, producer, rx_tail).await
         }
     }
 }
 
+pub(crate) fn text_tokenizer(
+    settings: &Option<TextParserSettings>,
+) -> Result<StringTokenizer, NativeError> {
+    let settings = settings.clone().unwrap_or_default();
+    let tokenizer = StringTokenizer::new(settings.encoding);
+    match settings.record_start {
+        Some(record_start) => tokenizer
+            .with_record_start(&record_start)
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Configuration,
+                message: Some(format!("Invalid record start pattern: {e}")),
+            }),
+        None => Ok(tokenizer),
+    }
+}
+
+pub(crate) fn regex_parser(settings: &RegexParserSettings) -> Result<RegexParser, NativeError> {
+    let parser = RegexParser::new(&settings.pattern);
+    match &settings.timestamp {
+        Some(capture) => parser
+            .and_then(|parser| parser.with_timestamp(capture, settings.timestamp_format.clone())),
+        None => parser,
+    }
+    .map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Configuration,
+        message: Some(e.to_string()),
+    })
+}
+
+/// Messages of a source combining several origins (e.g. the interfaces of a network
+/// trace) are assigned to an own source per origin, which is named by the alias of
+/// the source and the origin.
+async fn origin_source_id(
+    state: &SessionStateAPI,
+    source_id: u16,
+    origin: String,
+    origins: &mut HashMap<String, u16>,
+) -> Result<u16, NativeError> {
+    if let Some(id) = origins.get(&origin) {
+        return Ok(*id);
+    }
+    let alias = state
+        .get_sources_definitions()
+        .await?
+        .into_iter()
+        .find(|source| source.id == source_id)
+        .map_or_else(|| source_id.to_string(), |source| source.alias);
+    let alias = format!("{alias}:{origin}");
+    let id = match state.get_source(&alias).await? {
+        Some(id) => id,
+        None => state.add_source(&alias).await?,
+    };
+    origins.insert(origin, id);
+    Ok(id)
+}
+
+/// Bytes which could not be parsed are collected and reported at most once per
+/// flush interval, so a corrupted source doesn't flood the client with errors.
+#[derive(Default)]
+struct LostBytes {
+    bytes: usize,
+    ranges: usize,
+    reported: Option<Instant>,
+}
+
+impl LostBytes {
+    fn add(&mut self, range: &Range<usize>) {
+        self.bytes += range.len();
+        self.ranges += 1;
+    }
+
+    /// reports the collected bytes, unless the last report is too recent and
+    /// `force` isn't set
+    async fn report(
+        &mut self,
+        operation_api: &OperationAPI,
+        state: &SessionStateAPI,
+        force: bool,
+    ) -> Result<(), NativeError> {
+        if self.ranges == 0 {
+            return Ok(());
+        }
+        if !force
+            && self
+                .reported
+                .is_some_and(|reported| reported.elapsed().as_millis() < FLUSH_TIMEOUT_IN_MS)
+        {
+            return Ok(());
+        }
+        state.flush_session_file().await?;
+        let (rows, _) = state.get_stream_len().await?;
+        operation_api.emit(CallbackEvent::OperationError {
+            uuid: operation_api.id(),
+            error: NativeError {
+                severity: Severity::WARNING,
+                kind: NativeErrorKind::ComputationFailed,
+                message: Some(format!(
+                    "{} bytes at {} positions could not be parsed and were skipped before row {}",
+                    self.bytes, self.ranges, rows
+                )),
+            },
+        });
+        self.bytes = 0;
+        self.ranges = 0;
+        self.reported = Some(Instant::now());
+        Ok(())
+    }
+}
+
 async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
     operation_api: OperationAPI,
     state: SessionStateAPI,
     source_id: u16,
-    mut producer: MessageProducer<T, P, S>,
+    producer: MessageProducer<T, P, S>,
     mut rx_tail: Option<Receiver<Result<(), tail::Error>>>,
 ) -> OperationResult<()> {
     use log::debug;
     state.set_session_file(None).await?;
     operation_api.processing();
     let cancel = operation_api.cancellation_token();
-    let stream = producer.as_stream();
+    let mut origins = HashMap::new();
+    let mut lost = LostBytes::default();
+    let mut producer = producer.with_follow(rx_tail.is_some());
+    // a followed source is done each time its end is reached, it's read once then
+    let mut file_read = false;
+    let stream = producer.as_stream_with_origin();
     futures::pin_mut!(stream);
     let cancel_on_tail = cancel.clone();
     while let Some(next) = select! {
         next_from_stream = async {
             match timeout(Duration::from_millis(FLUSH_TIMEOUT_IN_MS as u64), stream.next()).await {
                 Ok(item) => {
-                    if let Some((_, item)) = item {
-                        Some(Next::Item(item))
+                    if let Some((_, item, origin)) = item {
+                        Some(Next::Item(item, origin))
                     } else {
                         Some(Next::Waiting)
                     }
@@ -104,7 +351,13 @@ async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
         _ = cancel.cancelled() => None,
     } {
         match next {
-            Next::Item(item) => {
+            Next::Item(item, origin) => {
+                let source_id = match origin {
+                    Some(origin) => {
+                        origin_source_id(&state, source_id, origin, &mut origins).await?
+                    }
+                    None => source_id,
+                };
                 match item {
                     MessageStreamItem::Item(ParseYield::Message(item)) => {
                         state
@@ -126,7 +379,20 @@ async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
                     MessageStreamItem::Done => {
                         trace!("observe, message stream is done");
                         state.flush_session_file().await?;
-                        state.file_read().await?;
+                        lost.report(&operation_api, &state, true).await?;
+                        if !file_read {
+                            file_read = true;
+                            state.file_read().await?;
+                        }
+                        // a followed source is read again, once it was changed
+                        if let Some(rx_tail) = rx_tail.as_mut() {
+                            if select! {
+                                update = rx_tail.recv() => !matches!(update, Some(Ok(()))),
+                                _ = cancel_on_tail.cancelled() => true,
+                            } {
+                                break;
+                            }
+                        }
                     }
                     // MessageStreamItem::FileRead => {
                     //     state.file_read().await?;
@@ -134,6 +400,16 @@ async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
                     MessageStreamItem::Skipped => {
                         trace!("observe: skipped a message");
                     }
+                    MessageStreamItem::Lost(range) => {
+                        warn!(
+                            "observe: lost {} bytes ({:?}) of source {}",
+                            range.len(),
+                            range,
+                            source_id
+                        );
+                        lost.add(&range);
+                        lost.report(&operation_api, &state, false).await?;
+                    }
                     MessageStreamItem::Incomplete => {
                         trace!("observe: incomplete message");
                     }
@@ -145,28 +421,17 @@ async fn run_producer<T: LogMessage, P: Parser<T>, S: ByteSource>(
             Next::Timeout => {
                 if !state.is_closing() {
                     state.flush_session_file().await?;
+                    lost.report(&operation_api, &state, true).await?;
                 }
             }
             Next::Waiting => {
-                if let Some(mut rx_tail) = rx_tail.take() {
-                    if select! {
-                        next_from_stream = rx_tail.recv() => {
-                            if let Some(result) = next_from_stream {
-                                result.is_err()
-                            } else {
-                                true
-                            }
-                        },
-                        _ = cancel_on_tail.cancelled() => true,
-                    } {
-                        break;
-                    }
-                } else {
-                    break;
-                }
+                break;
             }
         }
     }
+    if !state.is_closing() {
+        lost.report(&operation_api, &state, true).await?;
+    }
     debug!("listen done");
     Ok(None)
 }
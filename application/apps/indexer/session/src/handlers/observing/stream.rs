@@ -1,17 +1,39 @@
 use crate::{
-    events::{NativeError, NativeErrorKind},
+    events::{CallbackEvent, NativeError, NativeErrorKind},
     handlers::observing,
     operations::{OperationAPI, OperationResult},
-    progress::Severity,
+    progress::{Notification, Progress, Severity},
     state::SessionStateAPI,
 };
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::{debug, warn};
 use sources::{
     command::process::ProcessSource,
-    factory::{ParserType, Transport},
+    factory::{ParserType, TcpClientPolicy, TcpListenConfig, Transport},
     producer::SdeReceiver,
     serial::serialport::SerialSource,
-    socket::{tcp::TcpSource, udp::UdpSource},
+    socket::{
+        syslog::SyslogSource,
+        tcp::{TcpEvent, TcpServer, TcpSource},
+        udp::UdpSource,
+    },
+    SourceFilter,
 };
+#[cfg(unix)]
+use sources::{
+    factory::UnixSocketKind,
+    socket::{fifo::FifoSource, unix::UnixSocketSource},
+};
+use std::time::Duration;
+use tokio::{
+    join, select,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+
+/// accepting clients is retried after this delay if it failed
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
 
 pub async fn observe_stream<'a>(
     operation_api: OperationAPI,
@@ -19,8 +41,34 @@ pub async fn observe_stream<'a>(
     uuid: &str,
     transport: &Transport,
     parser: &'a ParserType,
+    filter: Option<&SourceFilter>,
     rx_sde: Option<SdeReceiver>,
 ) -> OperationResult<()> {
+    // the syslog listeners put the sender in front of each message, which only
+    // the syslog parser knows about
+    if matches!(transport, Transport::Syslog(_)) && !matches!(parser, ParserType::Syslog) {
+        return Err(NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Configuration,
+            message: Some(String::from(
+                "Syslog listeners can be observed with the syslog parser only",
+            )),
+        });
+    }
+    if let Transport::TCP(settings) = transport {
+        if let Some(listen) = settings.listen.as_ref() {
+            return listen_tcp(
+                operation_api,
+                state,
+                uuid,
+                &settings.bind_addr,
+                listen,
+                parser,
+                filter,
+            )
+            .await;
+        }
+    }
     let source_id = state.add_source(uuid).await?;
     match transport {
         Transport::UDP(settings) => {
@@ -37,6 +85,7 @@ pub async fn observe_stream<'a>(
                 udp_source,
                 source_id,
                 parser,
+                filter,
                 rx_sde,
                 None,
             )
@@ -50,17 +99,116 @@ pub async fn observe_stream<'a>(
                     kind: NativeErrorKind::Interrupted,
                     message: Some(format!("{e}")),
                 })?;
+            let reconnect = match settings.reconnect.as_ref() {
+                Some(reconnect) => reconnect,
+                None => {
+                    return observing::run_source(
+                        operation_api,
+                        state,
+                        tcp_source,
+                        source_id,
+                        parser,
+                        filter,
+                        rx_sde,
+                        None,
+                    )
+                    .await
+                }
+            };
+            let (tx_events, rx_events) = unbounded_channel();
+            let tcp_source = tcp_source
+                .with_reconnect(reconnect.clone(), tx_events)
+                .map_err(|e| NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Interrupted,
+                    message: Some(format!("{e}")),
+                })?;
+            let (observed, _) = join!(
+                observing::run_source(
+                    operation_api.clone(),
+                    state.clone(),
+                    tcp_source,
+                    source_id,
+                    parser,
+                    filter,
+                    rx_sde,
+                    None,
+                ),
+                report_tcp_events(&operation_api, &state, rx_events)
+            );
+            observed
+        }
+        Transport::Syslog(settings) => {
+            let syslog_source =
+                SyslogSource::new(&settings.bind_addr)
+                    .await
+                    .map_err(|e| NativeError {
+                        severity: Severity::ERROR,
+                        kind: NativeErrorKind::Interrupted,
+                        message: Some(format!("{e}")),
+                    })?;
+            observing::run_source(
+                operation_api,
+                state,
+                syslog_source,
+                source_id,
+                parser,
+                filter,
+                rx_sde,
+                None,
+            )
+            .await
+        }
+        #[cfg(unix)]
+        Transport::UnixSocket(settings) => {
+            let unix_source = match settings.kind {
+                UnixSocketKind::Stream => UnixSocketSource::connect(&settings.path).await,
+                UnixSocketKind::Datagram => UnixSocketSource::bind(&settings.path),
+            }
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Interrupted,
+                message: Some(format!("{e}")),
+            })?;
+            observing::run_source(
+                operation_api,
+                state,
+                unix_source,
+                source_id,
+                parser,
+                filter,
+                rx_sde,
+                None,
+            )
+            .await
+        }
+        #[cfg(unix)]
+        Transport::Fifo(settings) => {
+            let fifo_source = FifoSource::new(&settings.path).map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Interrupted,
+                message: Some(format!("{e}")),
+            })?;
             observing::run_source(
                 operation_api,
                 state,
-                tcp_source,
+                fifo_source,
                 source_id,
                 parser,
+                filter,
                 rx_sde,
                 None,
             )
             .await
         }
+        #[cfg(not(unix))]
+        Transport::UnixSocket(_) | Transport::Fifo(_) => Err(NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Configuration,
+            message: Some(String::from(
+                "Unix sockets and named pipes are supported on unix systems only",
+            )),
+        }),
         Transport::Serial(settings) => {
             let serial_source = SerialSource::new(settings).map_err(|e| NativeError {
                 severity: Severity::ERROR,
@@ -73,11 +221,51 @@ pub async fn observe_stream<'a>(
                 serial_source,
                 source_id,
                 parser,
+                filter,
                 rx_sde,
                 None,
             )
             .await
         }
+        Transport::Process(settings) if settings.raw => {
+            let (process_source, stderr_source) = ProcessSource::new_raw(
+                settings.command.clone(),
+                settings.cwd.clone(),
+                settings.envs.clone(),
+            )
+            .await
+            .map_err(|e| NativeError {
+                severity: Severity::ERROR,
+                kind: NativeErrorKind::Interrupted,
+                message: Some(format!("{e}")),
+            })?;
+            // stderr is always text, whatever the parser for stdout is
+            let stderr_id = state.add_source(&format!("{uuid}:stderr")).await?;
+            let (stdout, stderr) = join!(
+                observing::run_source(
+                    operation_api.clone(),
+                    state.clone(),
+                    process_source,
+                    source_id,
+                    parser,
+                    filter,
+                    rx_sde,
+                    None,
+                ),
+                observing::run_source(
+                    operation_api,
+                    state,
+                    stderr_source,
+                    stderr_id,
+                    &ParserType::Text(None),
+                    None,
+                    None,
+                    None,
+                )
+            );
+            stderr?;
+            stdout
+        }
         Transport::Process(settings) => {
             let process_source = ProcessSource::new(
                 settings.command.clone(),
@@ -96,6 +284,7 @@ pub async fn observe_stream<'a>(
                 process_source,
                 source_id,
                 parser,
+                filter,
                 rx_sde,
                 None,
             )
@@ -103,3 +292,125 @@ pub async fn observe_stream<'a>(
         }
     }
 }
+
+/// accepts clients until the operation is cancelled. Every connection gets its own source id.
+/// A failing connection is closed without affecting the listener and other connections.
+async fn listen_tcp(
+    operation_api: OperationAPI,
+    state: SessionStateAPI,
+    uuid: &str,
+    bind_addr: &str,
+    listen: &TcpListenConfig,
+    parser: &ParserType,
+    filter: Option<&SourceFilter>,
+) -> OperationResult<()> {
+    let server = TcpServer::bind(bind_addr).await.map_err(|e| NativeError {
+        severity: Severity::ERROR,
+        kind: NativeErrorKind::Interrupted,
+        message: Some(format!("{e}")),
+    })?;
+    let cancel = operation_api.cancellation_token();
+    let mut connections = FuturesUnordered::new();
+    // allows to close the most recent connection in case it gets replaced
+    let mut recent: Option<CancellationToken> = None;
+    loop {
+        select! {
+            accepted = server.accept() => {
+                let (source, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Fail to accept TCP connection: {e}");
+                        select! {
+                            _ = sleep(ACCEPT_RETRY_DELAY) => continue,
+                            _ = cancel.cancelled() => break,
+                        }
+                    }
+                };
+                if !connections.is_empty() {
+                    match listen.policy {
+                        TcpClientPolicy::Reject => {
+                            warn!("Rejecting TCP connection from {peer}, another client is connected");
+                            continue;
+                        }
+                        TcpClientPolicy::Replace => {
+                            if let Some(closing) = recent.take() {
+                                debug!("Closing TCP connection, replaced by {peer}");
+                                closing.cancel();
+                            }
+                        }
+                        TcpClientPolicy::Multiplex => {}
+                    }
+                }
+                let source_id = state.add_source(&format!("{uuid}:{peer}")).await?;
+                let closing = CancellationToken::new();
+                recent = Some(closing.clone());
+                let operation_api = operation_api.clone();
+                let state = state.clone();
+                connections.push(async move {
+                    let done = select! {
+                        done = observing::run_source(
+                            operation_api,
+                            state,
+                            source,
+                            source_id,
+                            parser,
+                            filter,
+                            None,
+                            None,
+                        ) => done,
+                        _ = closing.cancelled() => Ok(None),
+                    };
+                    (peer, done)
+                });
+            }
+            Some((peer, done)) = connections.next() => {
+                if let Err(e) = done {
+                    warn!("TCP connection from {peer} closed: {e:?}");
+                }
+            }
+            _ = cancel.cancelled() => break,
+        }
+    }
+    Ok(None)
+}
+
+/// reports losing and reestablishing the connection together with the row
+/// at which it happened. Ends as soon as the source is dropped.
+async fn report_tcp_events(
+    operation_api: &OperationAPI,
+    state: &SessionStateAPI,
+    mut rx_events: UnboundedReceiver<TcpEvent>,
+) {
+    while let Some(event) = rx_events.recv().await {
+        let (severity, content) = match event {
+            TcpEvent::Disconnected(reason) => (
+                Severity::WARNING,
+                format!("Connection lost ({reason}), reconnecting"),
+            ),
+            TcpEvent::Reconnected(attempts) => (
+                Severity::WARNING,
+                format!("Reconnected after {attempts} attempt(s)"),
+            ),
+            TcpEvent::GaveUp(attempts) => (
+                Severity::ERROR,
+                format!("Fail to reconnect after {attempts} attempt(s)"),
+            ),
+        };
+        if let Err(e) = state.flush_session_file().await {
+            warn!("Fail to flush session file: {e:?}");
+        }
+        let line = state
+            .get_stream_len()
+            .await
+            .ok()
+            .map(|(rows, _)| rows as usize);
+        operation_api.emit(CallbackEvent::Progress {
+            uuid: operation_api.id(),
+            progress: Progress::Notification(Notification {
+                severity,
+                content,
+                line,
+            }),
+        });
+    }
+}
@@ -18,7 +18,7 @@ use processor::{
     search::searchers::{regular::RegularSearchHolder, values::ValueSearchHolder},
 };
 use sources::factory::ObserveOptions;
-use std::{collections::HashMap, fmt::Display, ops::RangeInclusive, path::PathBuf};
+use std::{collections::HashMap, fmt::Display, fs::File, ops::RangeInclusive, path::PathBuf};
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     oneshot,
@@ -31,6 +31,7 @@ use super::values::graph::CandlePoint;
 pub enum Api {
     SetSessionFile((Option<PathBuf>, oneshot::Sender<Result<(), NativeError>>)),
     GetSessionFile(oneshot::Sender<Result<PathBuf, NativeError>>),
+    UnlinkSessionFile((Option<File>, oneshot::Sender<Result<u64, NativeError>>)),
     WriteSessionFile((u16, String, oneshot::Sender<Result<(), NativeError>>)),
     FlushSessionFile(oneshot::Sender<Result<(), NativeError>>),
     GetSessionFileOrigin(oneshot::Sender<Result<Option<SessionFileOrigin>, NativeError>>),
@@ -160,6 +161,7 @@ impl Display for Api {
             match self {
                 Self::SetSessionFile(_) => "SetSessionFile",
                 Self::GetSessionFile(_) => "GetSessionFile",
+                Self::UnlinkSessionFile(_) => "UnlinkSessionFile",
                 Self::WriteSessionFile(_) => "WriteSessionFile",
                 Self::FlushSessionFile(_) => "FlushSessionFile",
                 Self::GetSessionFileOrigin(_) => "GetSessionFileOrigin",
@@ -371,6 +373,13 @@ impl SessionStateAPI {
         self.exec_operation(Api::GetSessionFile(tx), rx).await?
     }
 
+    /// replaces the linked session file by a generated one, see `SessionFile::unlink`
+    pub async fn unlink_session_file(&self, content: Option<File>) -> Result<u64, NativeError> {
+        let (tx, rx) = oneshot::channel();
+        self.exec_operation(Api::UnlinkSessionFile((content, tx)), rx)
+            .await?
+    }
+
     pub async fn write_session_file(&self, source_id: u16, msg: String) -> Result<(), NativeError> {
         let (tx, rx) = oneshot::channel();
         self.exec_operation(Api::WriteSessionFile((source_id, msg, tx)), rx)
@@ -39,7 +39,9 @@ pub use indexes::{
 };
 use observed::Observed;
 use searchers::{SearcherState, Searchers};
-pub use session_file::{GrabbedElement, SessionFile, SessionFileOrigin, SessionFileState};
+pub use session_file::{
+    GrabbedElement, SessionFile, SessionFileOrigin, SessionFileState, TRUNCATED_ROW,
+};
 pub use source_ids::SourceDefinition;
 pub use values::Values;
 
@@ -376,6 +378,15 @@ pub async fn run(
                     NativeError::channel("Failed to response to Api::SetSessionFile")
                 })?;
             }
+            Api::UnlinkSessionFile((content, tx_response)) => {
+                let unlink_res = state.session_file.unlink(content);
+                if let (Ok(_), Ok(filename)) = (&unlink_res, state.session_file.filename()) {
+                    state.attachments.set_dest_path(filename);
+                }
+                tx_response.send(unlink_res).map_err(|_| {
+                    NativeError::channel("Failed to respond to Api::UnlinkSessionFile")
+                })?;
+            }
             Api::GetSessionFile(tx_response) => {
                 tx_response
                     .send(state.session_file.filename())
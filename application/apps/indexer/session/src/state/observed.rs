@@ -1,6 +1,13 @@
-use sources::factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType};
+use sources::{
+    factory::{FileFormat, ObserveOptions, ObserveOrigin, ParserType},
+    SourceFilter,
+};
 use std::path::PathBuf;
 
+/// an observed file with the parser, format and the filter of packets (for network
+/// traces) it was observed with
+pub type ObservedFile = (ParserType, FileFormat, PathBuf, Option<SourceFilter>);
+
 #[derive(Debug, Clone)]
 pub struct Observed {
     pub executed: Vec<ObserveOptions>,
@@ -18,27 +25,46 @@ impl Observed {
     pub fn is_file_based_export_possible(&self) -> bool {
         let mut possibility = true;
         self.executed.iter().for_each(|opt| {
-            if matches!(opt.origin, ObserveOrigin::Stream(..)) {
+            // archive members can only be read through the archive and the files
+            // of a directory aren't known upfront
+            if matches!(
+                opt.origin,
+                ObserveOrigin::Stream(..)
+                    | ObserveOrigin::Archive(..)
+                    | ObserveOrigin::Directory { .. }
+            ) {
+                possibility = false;
+            }
+            // rows of split connections aren't in the order of the file anymore
+            if opt.split_connections {
                 possibility = false;
             }
         });
         possibility
     }
 
-    pub fn get_files(&self) -> Vec<(ParserType, FileFormat, PathBuf)> {
-        let mut files: Vec<(ParserType, FileFormat, PathBuf)> = vec![];
+    pub fn get_files(&self) -> Vec<ObservedFile> {
+        let mut files: Vec<ObservedFile> = vec![];
         self.executed.iter().for_each(|opt| match &opt.origin {
-            ObserveOrigin::File(_, file_format, filename) => {
-                files.push((opt.parser.clone(), file_format.clone(), filename.clone()))
-            }
+            ObserveOrigin::File(_, file_format, filename) => files.push((
+                opt.parser.clone(),
+                file_format.clone(),
+                filename.clone(),
+                opt.filter.clone(),
+            )),
             ObserveOrigin::Concat(list) => {
                 files.append(
                     &mut list
                         .iter()
                         .map(|(_, file_format, filename)| {
-                            (opt.parser.clone(), file_format.clone(), filename.clone())
+                            (
+                                opt.parser.clone(),
+                                file_format.clone(),
+                                filename.clone(),
+                                opt.filter.clone(),
+                            )
                         })
-                        .collect::<Vec<(ParserType, FileFormat, PathBuf)>>(),
+                        .collect::<Vec<ObservedFile>>(),
                 );
             }
             _ => {}
@@ -4,7 +4,7 @@ use crate::{
     paths,
     progress::Severity,
 };
-use log::debug;
+use log::{debug, warn};
 use processor::{
     grabber::{Grabber, LineRange},
     text_source::TextFileSource,
@@ -12,7 +12,7 @@ use processor::{
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
-    io::{BufWriter, Write},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
     path::PathBuf,
     time::Instant,
 };
@@ -20,6 +20,8 @@ use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 pub const FLUSH_DATA_IN_MS: u128 = 500;
+/// content of the rows read from a linked file before it was truncated
+pub const TRUNCATED_ROW: &str = "<not available anymore, the file was truncated>";
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GrabbedElement {
@@ -92,20 +94,8 @@ impl SessionFile {
                 self.filename = Some(SessionFileOrigin::Linked(filename.clone()));
                 filename
             } else {
-                let streams = paths::get_streams_dir()?;
-                let filename = streams.join(format!("{}.session", Uuid::new_v4()));
-                debug!("Session file setup: {}", filename.to_string_lossy());
-                self.writer = Some(BufWriter::new(File::create(&filename).map_err(|e| {
-                    NativeError {
-                        severity: Severity::ERROR,
-                        kind: NativeErrorKind::Io,
-                        message: Some(format!(
-                            "Fail to create session writer for {}: {}",
-                            filename.to_string_lossy(),
-                            e
-                        )),
-                    }
-                })?));
+                let (filename, writer) = Self::create()?;
+                self.writer = Some(writer);
                 self.filename = Some(SessionFileOrigin::Generated(filename.clone()));
                 filename
             };
@@ -116,6 +106,94 @@ impl SessionFile {
         }
     }
 
+    fn create() -> Result<(PathBuf, BufWriter<File>), NativeError> {
+        let streams = paths::get_streams_dir()?;
+        let filename = streams.join(format!("{}.session", Uuid::new_v4()));
+        debug!("Session file setup: {}", filename.to_string_lossy());
+        let writer = BufWriter::new(File::create(&filename).map_err(|e| NativeError {
+            severity: Severity::ERROR,
+            kind: NativeErrorKind::Io,
+            message: Some(format!(
+                "Fail to create session writer for {}: {}",
+                filename.to_string_lossy(),
+                e
+            )),
+        })?);
+        Ok((filename, writer))
+    }
+
+    /// Replaces the linked file by a generated session file, which can be written
+    /// to. It's used if the linked file was rotated or truncated and the content of
+    /// the file has to be added from now on.
+    /// - if it was rotated, the content read so far is copied from `content`, which
+    ///   is the linked file opened before it was rotated
+    /// - if it was truncated (`content` is `None`), the content read so far isn't
+    ///   available anymore. Each of these rows is replaced by `TRUNCATED_ROW`, so rows
+    ///   already known (e.g. by search results or bookmarks) keep their positions.
+    ///
+    /// Returns the position in the file to continue reading from.
+    pub fn unlink(&mut self, content: Option<File>) -> Result<u64, NativeError> {
+        let linked = match &self.filename {
+            Some(SessionFileOrigin::Linked(filename)) => filename.clone(),
+            _ => {
+                return Err(NativeError {
+                    severity: Severity::ERROR,
+                    kind: NativeErrorKind::Grabber,
+                    message: Some(String::from("Session file isn't linked, cannot unlink")),
+                })
+            }
+        };
+        let (filename, mut writer) = Self::create()?;
+        let (grabber, position) = match content {
+            Some(mut content) => {
+                let bytes = self.read_bytes();
+                content.seek(SeekFrom::Start(0))?;
+                let copied = std::io::copy(&mut (&mut content).take(bytes), &mut writer)?;
+                writer.flush()?;
+                if copied < bytes {
+                    drop(writer);
+                    if let Err(e) = std::fs::remove_file(&filename) {
+                        warn!("Fail to remove {}: {e}", filename.to_string_lossy());
+                    }
+                    return Err(NativeError {
+                        severity: Severity::ERROR,
+                        kind: NativeErrorKind::Io,
+                        message: Some(format!(
+                            "Content of {} isn't available anymore, it was truncated",
+                            linked.to_string_lossy()
+                        )),
+                    });
+                }
+                let mut grabber = Grabber::lazy(TextFileSource::new(&filename))?;
+                grabber.metadata = self
+                    .grabber
+                    .as_mut()
+                    .and_then(|grabber| grabber.metadata.take());
+                (grabber, bytes)
+            }
+            None => {
+                let rows = self.len();
+                warn!(
+                    "{} was truncated, content of {rows} rows read so far isn't available anymore",
+                    linked.to_string_lossy()
+                );
+                for _ in 0..rows {
+                    writer.write_all(TRUNCATED_ROW.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                }
+                writer.flush()?;
+                // the rows are assigned to their sources already
+                let mut grabber = Grabber::lazy(TextFileSource::new(&filename))?;
+                grabber.update_from_file(None)?;
+                (grabber, 0)
+            }
+        };
+        self.grabber = Some(Box::new(grabber));
+        self.writer = Some(writer);
+        self.filename = Some(SessionFileOrigin::Generated(filename));
+        Ok(position)
+    }
+
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&mut self) -> u64 {
         if let Some(ref grabber) = self.grabber {
@@ -1,13 +1,20 @@
-use log::error;
-use std::path::Path;
+use log::warn;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use sources::binary::rotating::file_id;
+use std::{ffi::OsString, io::ErrorKind, path::Path, sync::Arc};
 use thiserror::Error as ThisError;
 use tokio::{
-    sync::mpsc::Sender,
-    time::{timeout, Duration},
+    select,
+    sync::{mpsc::Sender, Notify},
+    time::{sleep, Duration},
 };
 use tokio_util::sync::CancellationToken;
 
+/// interval of checking the file, if it cannot be watched
 const TRACKING_INTERVAL_MS: u64 = 1000;
+/// interval of checking a watched file, in case a change isn't reported
+/// (e.g. on network file systems)
+const WATCHED_TRACKING_INTERVAL_MS: u64 = 5000;
 
 #[derive(ThisError, Debug)]
 pub enum Error {
@@ -17,37 +24,159 @@ pub enum Error {
     Channel(String),
 }
 
+/// the file a path points to and its size
+#[derive(PartialEq, Eq)]
+struct Snapshot {
+    file_id: Option<(u64, u64)>,
+    size: u64,
+}
+
+async fn snapshot(path: &Path) -> Result<Option<Snapshot>, Error> {
+    match tokio::fs::metadata(path).await {
+        Ok(md) => Ok(Some(Snapshot {
+            file_id: file_id(&md),
+            size: md.len(),
+        })),
+        // the file might be moved away while it's rotated
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::Io(e.to_string())),
+    }
+}
+
+/// Notifies about changes of a file or of the files in a directory. To notice a
+/// rotation of a file as well, its directory is watched.
+pub(crate) struct FileWatcher {
+    // events are reported as long as the watcher exists
+    _watcher: RecommendedWatcher,
+    changed: Arc<Notify>,
+}
+
+impl FileWatcher {
+    pub(crate) fn file(path: &Path) -> Result<Self, notify::Error> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        Self::new(dir, path.file_name().map(|name| name.to_os_string()))
+    }
+
+    pub(crate) fn directory(path: &Path) -> Result<Self, notify::Error> {
+        Self::new(path, None)
+    }
+
+    /// watches `dir`, if `filename` is given only changes of that file are reported
+    fn new(dir: &Path, filename: Option<OsString>) -> Result<Self, notify::Error> {
+        let changed = Arc::new(Notify::new());
+        let notifier = changed.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) => {
+                    if filename.is_none()
+                        || event
+                            .paths
+                            .iter()
+                            .any(|path| path.file_name() == filename.as_deref())
+                    {
+                        notifier.notify_one();
+                    }
+                }
+                Err(err) => {
+                    warn!("File watcher error: {err}");
+                    notifier.notify_one();
+                }
+            })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            changed,
+        })
+    }
+}
+
+/// resolves once the watcher reports a change or the tracking interval has passed,
+/// which is shorter if there is no watcher and changes have to be polled
+pub(crate) async fn next_check(watcher: Option<&FileWatcher>) {
+    match watcher {
+        Some(watcher) => {
+            select! {
+                _ = watcher.changed.notified() => {},
+                _ = sleep(Duration::from_millis(WATCHED_TRACKING_INTERVAL_MS)) => {},
+            }
+        }
+        None => sleep(Duration::from_millis(TRACKING_INTERVAL_MS)).await,
+    }
+}
+
+/// Sends an update signal each time the file at the path grows, shrinks (is
+/// truncated) or is replaced by another file (is rotated). While the file is
+/// missing, e.g. in the middle of a rotation, no signal is sent.
+/// Changes are reported by the file system watcher of the platform (inotify on
+/// linux); if the file cannot be watched, it's polled.
 pub async fn track(
     path: &Path,
     tx_update: Sender<Result<(), Error>>,
     shutdown: CancellationToken,
 ) -> Result<(), Error> {
-    use tokio::fs;
-    let mut size = fs::metadata(path)
-        .await
-        .map(|md| md.len())
-        .map_err(|e| Error::Io(e.to_string()))?;
+    let mut state = snapshot(path)
+        .await?
+        .ok_or_else(|| Error::Io(format!("File {} doesn't exist", path.display())))?;
+    let watcher = FileWatcher::file(path)
+        .map_err(|err| {
+            warn!(
+                "Fail to watch {}, changes are polled instead: {err}",
+                path.display()
+            );
+        })
+        .ok();
     loop {
-        match timeout(
-            Duration::from_millis(TRACKING_INTERVAL_MS),
-            shutdown.cancelled(),
-        )
-        .await
-        {
-            Ok(_) => break,
-            Err(_) => {
-                let updated = fs::metadata(path)
-                    .await
-                    .map(|md| md.len())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-                if updated != size {
-                    size = updated;
-                    if let Err(err) = tx_update.send(Ok(())).await {
-                        return Err(Error::Channel(format!("Fail to send update signal: {err}")));
-                    }
-                }
-            }
+        select! {
+            _ = shutdown.cancelled() => break,
+            _ = next_check(watcher.as_ref()) => {},
         };
+        let updated = snapshot(path).await?;
+        if let Some(updated) = updated.filter(|updated| updated != &state) {
+            state = updated;
+            if let Err(err) = tx_update.send(Ok(())).await {
+                return Err(Error::Channel(format!("Fail to send update signal: {err}")));
+            }
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio::{sync::mpsc::channel, time::timeout};
+
+    #[tokio::test]
+    async fn test_track_appended() {
+        let path = std::env::temp_dir().join(format!("{}.log", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        let (tx_update, mut rx_update) = channel(1);
+        let shutdown = CancellationToken::new();
+        let tracker = tokio::spawn({
+            let path = path.clone();
+            let shutdown = shutdown.clone();
+            async move { track(&path, tx_update, shutdown).await }
+        });
+        // let the watcher start
+        sleep(Duration::from_millis(100)).await;
+        for _ in 0..3 {
+            file.write_all(b"line\n").unwrap();
+            // faster than the polling, as the change is reported by the watcher
+            timeout(
+                Duration::from_millis(TRACKING_INTERVAL_MS / 2),
+                rx_update.recv(),
+            )
+            .await
+            .expect("change isn't reported")
+            .unwrap()
+            .unwrap();
+        }
+        shutdown.cancel();
+        tracker.await.unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -139,6 +139,16 @@ impl UnboundSessionAPI {
             .await
     }
 
+    pub async fn get_archive_members(
+        &self,
+        id: u64,
+        path: String,
+    ) -> Result<CommandOutcome<String>, ComputationError> {
+        let (tx_results, rx_results) = oneshot::channel();
+        self.process_command(id, rx_results, Command::GetArchiveMembers(path, tx_results))
+            .await
+    }
+
     pub async fn get_dlt_stats(
         &self,
         id: u64,
@@ -0,0 +1,24 @@
+use super::CommandOutcome;
+use crate::{events::ComputationError, unbound::signal::Signal};
+use log::warn;
+use sources::binary::archive::{self, ArchiveError};
+use std::path::Path;
+
+/// lists the files of a zip or tar archive as JSON
+pub fn list_members(
+    file: &str,
+    signal: Signal,
+) -> Result<CommandOutcome<String>, ComputationError> {
+    let members = match archive::list(Path::new(file), || signal.is_cancelling()) {
+        Ok(members) => members,
+        Err(ArchiveError::Cancelled) => {
+            warn!("Operation of listing members of: {file} has been cancelled");
+            return Ok(CommandOutcome::Cancelled);
+        }
+        Err(e) => return Err(ComputationError::IoOperation(e.to_string())),
+    };
+    Ok(CommandOutcome::Finished(
+        serde_json::to_string(&members)
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
+    ))
+}
@@ -1,3 +1,4 @@
+mod archive;
 mod cancel_test;
 mod checksum;
 mod dlt;
@@ -69,6 +70,10 @@ pub enum Command {
         String,
         oneshot::Sender<Result<CommandOutcome<String>, ComputationError>>,
     ),
+    GetArchiveMembers(
+        String,
+        oneshot::Sender<Result<CommandOutcome<String>, ComputationError>>,
+    ),
     GetDltStats(
         Vec<String>,
         oneshot::Sender<Result<CommandOutcome<String>, ComputationError>>,
@@ -105,6 +110,7 @@ impl std::fmt::Display for Command {
                 Command::GetContextEnvvars(_) => "Getting context envvars",
                 Command::SerialPortsList(_) => "Getting serial ports list",
                 Command::Checksum(_, _) => "Calculating file's checksum",
+                Command::GetArchiveMembers(_, _) => "Getting archive's files",
                 Command::GetDltStats(_, _) => "Getting dlt stats",
                 Command::GetSomeipStatistic(_, _) => "Getting someip statistic",
                 Command::GetRegexError(_, _) => "Checking regex",
@@ -136,9 +142,12 @@ pub async fn process(command: Command, signal: Signal) {
             tx.send(regex::get_filter_error(filter, signal)).is_err()
         }
         Command::Checksum(file, tx) => tx.send(checksum::checksum(&file, signal)).is_err(),
+        Command::GetArchiveMembers(file, tx) => {
+            tx.send(archive::list_members(&file, signal)).is_err()
+        }
         Command::GetDltStats(files, tx) => tx.send(dlt::stats(files, signal)).is_err(),
         Command::GetSomeipStatistic(files, tx) => {
-            tx.send(get_someip_statistic(files, signal)).is_err()
+            tx.send(get_someip_statistic(files, signal).await).is_err()
         }
         Command::GetShellProfiles(tx) => tx.send(shells::get_valid_profiles(signal)).is_err(),
         Command::GetContextEnvvars(tx) => tx.send(shells::get_context_envvars(signal)).is_err(),
@@ -160,6 +169,7 @@ pub fn err(command: Command, err: ComputationError) {
         Command::SpawnProcess(_path, _args, tx) => tx.send(Err(err)).is_err(),
         Command::GetRegexError(_filter, tx) => tx.send(Err(err)).is_err(),
         Command::Checksum(_file, tx) => tx.send(Err(err)).is_err(),
+        Command::GetArchiveMembers(_file, tx) => tx.send(Err(err)).is_err(),
         Command::GetDltStats(_files, tx) => tx.send(Err(err)).is_err(),
         Command::GetSomeipStatistic(_files, tx) => tx.send(Err(err)).is_err(),
         Command::GetShellProfiles(tx) => tx.send(Err(err)).is_err(),
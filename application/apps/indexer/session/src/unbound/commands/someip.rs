@@ -1,44 +1,80 @@
 use super::CommandOutcome;
 use crate::{events::ComputationError, unbound::signal::Signal};
+use log::{error, warn};
+use parsers::someip::statistic::SomeipStatistic;
+use sources::{
+    binary::pcap::{legacy::PcapLegacyByteSource, ng::PcapngByteSource},
+    ByteSource,
+};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
 
-pub fn get_someip_statistic(
-    _files: Vec<String>,
-    _signal: Signal,
+/// every pcapng file starts with a section header block of this type
+const PCAPNG_BLOCK_TYPE: [u8; 4] = [0x0A, 0x0D, 0x0D, 0x0A];
+
+pub async fn get_someip_statistic(
+    files: Vec<String>,
+    signal: Signal,
 ) -> Result<CommandOutcome<String>, ComputationError> {
-    Err(ComputationError::OperationNotSupported("NYI".into()))
-    // use parsers::someip::{read_someip_statistic_from_pcapng, SomeipStatistic};
-    // use log::{error, warn};
-    // use std::path::Path;
+    let mut statistic = SomeipStatistic::new();
+    for file in files.iter() {
+        if signal.is_cancelling() {
+            break;
+        }
+        match read_statistic(Path::new(file), &signal).await {
+            Ok(result) => statistic.merge(result),
+            Err(err) => {
+                error!("Fail to get statistic for: {file}");
+                return Err(ComputationError::IoOperation(err));
+            }
+        }
+    }
+    if signal.is_cancelling() {
+        warn!("Operation of getting statistic for: {files:?} has been cancelled");
+        return Ok(CommandOutcome::Cancelled);
+    }
+    Ok(CommandOutcome::Finished(
+        serde_json::to_string(&statistic)
+            .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
+    ))
+}
+
+/// statistic of the SOME/IP messages of a pcap or pcapng file
+async fn read_statistic(file: &Path, signal: &Signal) -> Result<SomeipStatistic, String> {
+    let mut block_type = [0u8; 4];
+    File::open(file)
+        .and_then(|mut input| input.read_exact(&mut block_type))
+        .map_err(|e| format!("{}: {e}", file.display()))?;
+    let input = BufReader::new(File::open(file).map_err(|e| e.to_string())?);
+    if block_type == PCAPNG_BLOCK_TYPE {
+        let mut source = PcapngByteSource::new(input).map_err(|e| e.to_string())?;
+        collect(&mut source, signal).await
+    } else {
+        let mut source = PcapLegacyByteSource::new(input).map_err(|e| e.to_string())?;
+        collect(&mut source, signal).await
+    }
+}
 
-    // let mut statistic = SomeipStatistic::new();
-    // let mut error: Option<String> = None;
-    // warn!("Getting statistic for: {files:?}");
-    // files.iter().for_each(|file| {
-    //     if error.is_some() {
-    //         return;
-    //     }
-    //     if signal.is_cancelling() {
-    //         return;
-    //     }
-    //     match read_someip_statistic_from_pcapng(Path::new(&file), &signal.token()) {
-    //         Ok(result) => {
-    //             statistic.merge(result);
-    //         }
-    //         Err(err) => {
-    //             error = Some(err.to_string());
-    //         }
-    //     }
-    // });
-    // if let Some(err) = error {
-    //     error!("Fail to get statistic for: {files:?}");
-    //     return Err(ComputationError::IoOperation(err));
-    // }
-    // if signal.is_cancelling() {
-    //     warn!("Operation of geting statistic for: {files:?} has been cancelled");
-    //     return Ok(CommandOutcome::Cancelled);
-    // }
-    // Ok(CommandOutcome::Finished(
-    //     serde_json::to_string(&statistic)
-    //         .map_err(|e| ComputationError::IoOperation(e.to_string()))?,
-    // ))
+async fn collect<S: ByteSource>(
+    source: &mut S,
+    signal: &Signal,
+) -> Result<SomeipStatistic, String> {
+    let mut statistic = SomeipStatistic::new();
+    while !signal.is_cancelling()
+        && source
+            .reload(None)
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some()
+    {
+        // an incomplete message is kept until the source provides the missing bytes
+        let consumed = statistic.collect(source.current_slice());
+        if consumed > 0 {
+            source.consume(consumed);
+        }
+    }
+    Ok(statistic)
 }
@@ -0,0 +1,397 @@
+use crate::binary::compressed::DecompressingReader;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Take},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+use zip::{result::ZipError, CompressionMethod, ZipArchive};
+
+const ZIP_MAGIC: &[u8] = &[0x50, 0x4B, 0x03, 0x04]; // "PK" + 0x03 0x04
+const ZIP_EMPTY_MAGIC: &[u8] = &[0x50, 0x4B, 0x05, 0x06];
+const TAR_MAGIC: &[u8] = b"ustar";
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_BLOCK_SIZE: usize = 512;
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] ZipError),
+    #[error("{0} is neither a zip nor a tar archive")]
+    NotAnArchive(String),
+    #[error("Archive has no file {0}")]
+    MemberNotFound(String),
+    #[error("Compression of {0} is not supported")]
+    UnsupportedCompression(String),
+    #[error("Listing the archive was cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    /// tar archives might be compressed as a whole (e.g. `.tar.gz`)
+    Tar,
+}
+
+impl ArchiveFormat {
+    pub fn of_file(path: &Path) -> Result<Option<Self>, ArchiveError> {
+        let mut head = Vec::with_capacity(TAR_BLOCK_SIZE);
+        File::open(path)?
+            .take(ZIP_MAGIC.len() as u64)
+            .read_to_end(&mut head)?;
+        if head.starts_with(ZIP_MAGIC) || head.starts_with(ZIP_EMPTY_MAGIC) {
+            return Ok(Some(ArchiveFormat::Zip));
+        }
+        head.clear();
+        DecompressingReader::new(File::open(path)?)?
+            .take(TAR_BLOCK_SIZE as u64)
+            .read_to_end(&mut head)?;
+        Ok(
+            if head.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len()) == Some(TAR_MAGIC) {
+                Some(ArchiveFormat::Tar)
+            } else {
+                None
+            },
+        )
+    }
+}
+
+/// a file in an archive
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ArchiveMember {
+    /// path of the file inside of the archive
+    pub name: String,
+    /// uncompressed size
+    pub size: u64,
+}
+
+/// where the content of a member starts, used to read it without extracting it
+struct Location {
+    offset: u64,
+    compressed_size: u64,
+    compression: CompressionMethod,
+}
+
+fn archive_format(path: &Path) -> Result<ArchiveFormat, ArchiveError> {
+    ArchiveFormat::of_file(path)?
+        .ok_or_else(|| ArchiveError::NotAnArchive(path.to_string_lossy().to_string()))
+}
+
+/// lists all files of a zip or tar archive, directories are left out. Listing
+/// stops with [`ArchiveError::Cancelled`] as soon as `cancelled` returns true.
+pub fn list(path: &Path, cancelled: impl Fn() -> bool) -> Result<Vec<ArchiveMember>, ArchiveError> {
+    Ok(members(path, archive_format(path)?, cancelled)?
+        .into_iter()
+        .map(|(member, _)| member)
+        .collect())
+}
+
+fn members(
+    path: &Path,
+    format: ArchiveFormat,
+    cancelled: impl Fn() -> bool,
+) -> Result<Vec<(ArchiveMember, Location)>, ArchiveError> {
+    let mut members = vec![];
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+            for i in 0..archive.len() {
+                if cancelled() {
+                    return Err(ArchiveError::Cancelled);
+                }
+                let file = archive.by_index_raw(i)?;
+                if file.is_dir() {
+                    continue;
+                }
+                members.push((
+                    ArchiveMember {
+                        name: file.name().to_owned(),
+                        size: file.size(),
+                    },
+                    Location {
+                        offset: file.data_start(),
+                        compressed_size: file.compressed_size(),
+                        compression: file.compression(),
+                    },
+                ));
+            }
+        }
+        ArchiveFormat::Tar => {
+            let mut archive = tar::Archive::new(DecompressingReader::new(File::open(path)?)?);
+            for entry in archive.entries()? {
+                if cancelled() {
+                    return Err(ArchiveError::Cancelled);
+                }
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                members.push((
+                    ArchiveMember {
+                        name: entry.path()?.to_string_lossy().to_string(),
+                        size: entry.size(),
+                    },
+                    Location {
+                        offset: entry.raw_file_position(),
+                        compressed_size: entry.size(),
+                        compression: CompressionMethod::Stored,
+                    },
+                ));
+            }
+        }
+    }
+    Ok(members)
+}
+
+enum Content {
+    Stored(Take<BufReader<File>>),
+    Deflated(flate2::read::DeflateDecoder<Take<BufReader<File>>>),
+    Tar(TarMember),
+}
+
+/// the (decompressed) stream of a tar archive, which is shared by the readers of
+/// its members. Members read in the order of the archive are read in one pass.
+struct TarStream {
+    reader: DecompressingReader<File>,
+    position: u64,
+}
+
+impl TarStream {
+    fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            reader: DecompressingReader::new(File::open(path)?)?,
+            position: 0,
+        })
+    }
+
+    fn skip_to(&mut self, position: u64) -> io::Result<()> {
+        let skip = position - self.position;
+        let skipped = io::copy(&mut (&mut self.reader).take(skip), &mut io::sink())?;
+        self.position += skipped;
+        if skipped < skip {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "tar archive ends before member",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// a member of a tar archive, read from the shared stream
+struct TarMember {
+    path: PathBuf,
+    stream: Arc<Mutex<Option<TarStream>>>,
+    /// position of the next byte to read within the stream
+    position: u64,
+    end: u64,
+}
+
+impl Read for TarMember {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.end || buf.is_empty() {
+            return Ok(0);
+        }
+        let mut shared = self
+            .stream
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // the stream only starts over if a member before the current position is read
+        let stream = match shared.take() {
+            Some(stream) if stream.position <= self.position => stream,
+            _ => TarStream::open(&self.path)?,
+        };
+        let stream = shared.insert(stream);
+        stream.skip_to(self.position)?;
+        let max = (self.end - self.position).min(buf.len() as u64) as usize;
+        let len = stream.reader.read(&mut buf[..max])?;
+        stream.position += len as u64;
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+/// A zip or tar archive whose members are listed once, so that several of them
+/// can be read without scanning the archive again for each of them
+pub struct Archive {
+    path: PathBuf,
+    format: ArchiveFormat,
+    members: Vec<(ArchiveMember, Location)>,
+    tar_stream: Arc<Mutex<Option<TarStream>>>,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> Result<Self, ArchiveError> {
+        let format = archive_format(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            format,
+            members: members(path, format, || false)?,
+            tar_stream: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// all files of the archive, directories are left out
+    pub fn members(&self) -> impl Iterator<Item = &ArchiveMember> {
+        self.members.iter().map(|(member, _)| member)
+    }
+
+    /// index of the file `name` in the archive. Reading members in this order
+    /// avoids reading a compressed tar archive several times.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.members
+            .iter()
+            .position(|(member, _)| member.name == name)
+    }
+
+    /// reads the content of the file `name` without extracting it
+    pub fn reader(&self, name: &str) -> Result<ArchiveMemberReader, ArchiveError> {
+        let location = self
+            .members
+            .iter()
+            .find(|(member, _)| member.name == name)
+            .map(|(_, location)| location)
+            .ok_or_else(|| ArchiveError::MemberNotFound(name.to_owned()))?;
+        ArchiveMemberReader::new(self, name, location)
+    }
+}
+
+/// Reads the content of one file of an archive without extracting it
+pub struct ArchiveMemberReader {
+    content: Content,
+}
+
+impl ArchiveMemberReader {
+    fn new(archive: &Archive, name: &str, location: &Location) -> Result<Self, ArchiveError> {
+        let content = match archive.format {
+            ArchiveFormat::Zip => {
+                let mut file = BufReader::new(File::open(&archive.path)?);
+                file.seek(SeekFrom::Start(location.offset))?;
+                let data = file.take(location.compressed_size);
+                match location.compression {
+                    CompressionMethod::Stored => Content::Stored(data),
+                    CompressionMethod::Deflated => {
+                        Content::Deflated(flate2::read::DeflateDecoder::new(data))
+                    }
+                    _ => return Err(ArchiveError::UnsupportedCompression(name.to_owned())),
+                }
+            }
+            ArchiveFormat::Tar => Content::Tar(TarMember {
+                path: archive.path.clone(),
+                stream: archive.tar_stream.clone(),
+                position: location.offset,
+                end: location.offset + location.compressed_size,
+            }),
+        };
+        Ok(Self { content })
+    }
+}
+
+impl Read for ArchiveMemberReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.content {
+            Content::Stored(reader) => reader.read(buf),
+            Content::Deflated(reader) => reader.read(buf),
+            Content::Tar(reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, path::PathBuf};
+
+    const MEMBERS: &[(&str, &[u8])] = &[
+        ("logs/first.log", b"first\nlog\n"),
+        ("logs/second.dlt", b"DLT\x01\x00\xFF binary"),
+    ];
+
+    fn archive_path(extension: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}.{extension}", uuid::Uuid::new_v4()))
+    }
+
+    fn write_zip(path: &Path) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        zip.add_directory("logs/", Default::default()).unwrap();
+        for (i, (name, content)) in MEMBERS.iter().enumerate() {
+            let method = if i == 0 {
+                CompressionMethod::Deflated
+            } else {
+                CompressionMethod::Stored
+            };
+            zip.start_file(
+                *name,
+                zip::write::FileOptions::default().compression_method(method),
+            )
+            .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path) {
+        let encoder =
+            flate2::write::GzEncoder::new(File::create(path).unwrap(), Default::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, content) in MEMBERS {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *content).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_archive_members() {
+        for (extension, write) in [
+            ("zip", write_zip as fn(&Path)),
+            ("tar.gz", write_tar_gz as fn(&Path)),
+        ] {
+            let path = archive_path(extension);
+            write(&path);
+            assert_eq!(
+                list(&path, || false).unwrap(),
+                MEMBERS
+                    .iter()
+                    .map(|(name, content)| ArchiveMember {
+                        name: name.to_string(),
+                        size: content.len() as u64,
+                    })
+                    .collect::<Vec<ArchiveMember>>()
+            );
+            assert!(matches!(list(&path, || true), Err(ArchiveError::Cancelled)));
+            let archive = Archive::open(&path).unwrap();
+            assert_eq!(archive.members().count(), MEMBERS.len());
+            assert_eq!(archive.index_of(MEMBERS[1].0), Some(1));
+            // in the order of the archive, then again from its start
+            for (name, content) in MEMBERS.iter().chain(MEMBERS.iter()) {
+                let mut read = vec![];
+                archive
+                    .reader(name)
+                    .unwrap()
+                    .read_to_end(&mut read)
+                    .unwrap();
+                assert_eq!(&read, content);
+            }
+            assert!(matches!(
+                archive.reader("missing.log"),
+                Err(ArchiveError::MemberNotFound(_))
+            ));
+            std::fs::remove_file(&path).unwrap();
+        }
+        let path = archive_path("log");
+        std::fs::write(&path, MEMBERS[0].1).unwrap();
+        assert!(matches!(
+            list(&path, || false),
+            Err(ArchiveError::NotAnArchive(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+const XZ_MAGIC: &[u8] = &[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00];
+const BZIP2_MAGIC: &[u8] = &[0x42, 0x5A, 0x68]; // "BZh" followed by the block size '1'..'9'
+const MAX_MAGIC_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// detects the compression by the magic bytes at the start of the data
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if head.starts_with(XZ_MAGIC) {
+            Some(Compression::Xz)
+        } else if head.starts_with(BZIP2_MAGIC)
+            && matches!(head.get(BZIP2_MAGIC.len()), Some(b'1'..=b'9'))
+        {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    /// detects the compression of a file
+    pub fn of_file(path: &Path) -> Result<Option<Self>, std::io::Error> {
+        let mut head = Vec::with_capacity(MAX_MAGIC_LEN);
+        File::open(path)?
+            .take(MAX_MAGIC_LEN as u64)
+            .read_to_end(&mut head)?;
+        Ok(Compression::detect(&head))
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Compression::Gzip => "gzip",
+                Compression::Zstd => "zstd",
+                Compression::Xz => "xz",
+                Compression::Bzip2 => "bzip2",
+            }
+        )
+    }
+}
+
+/// Count of bytes read from the (compressed) input. Can be shared to
+/// report progress while the data is decompressed.
+#[derive(Debug, Clone, Default)]
+pub struct ReadPosition(Arc<AtomicU64>);
+
+impl ReadPosition {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+struct CountingReader<R: Read> {
+    inner: R,
+    position: ReadPosition,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.position.0.fetch_add(len as u64, Ordering::Relaxed);
+        Ok(len)
+    }
+}
+
+type Input<R> = BufReader<CountingReader<R>>;
+
+enum Decoder<R: Read> {
+    Plain(Input<R>),
+    Gzip(flate2::bufread::MultiGzDecoder<Input<R>>),
+    Zstd(zstd::stream::read::Decoder<'static, Input<R>>),
+    Xz(xz2::bufread::XzDecoder<Input<R>>),
+    Bzip2(bzip2::bufread::MultiBzDecoder<Input<R>>),
+}
+
+/// Reads gzip, zstd, xz and bzip2 compressed data as if it were uncompressed.
+/// The compression is detected by the magic bytes at the start of the input,
+/// any other input is passed on unchanged.
+/// Can be used as input of `BinaryByteSource` and the pcap sources.
+pub struct DecompressingReader<R: Read> {
+    decoder: Decoder<R>,
+    compression: Option<Compression>,
+    position: ReadPosition,
+}
+
+impl<R: Read> DecompressingReader<R> {
+    pub fn new(reader: R) -> Result<Self, std::io::Error> {
+        let position = ReadPosition::default();
+        let mut input = BufReader::new(CountingReader {
+            inner: reader,
+            position: position.clone(),
+        });
+        let compression = Compression::detect(input.fill_buf()?);
+        let decoder = match compression {
+            None => Decoder::Plain(input),
+            Some(Compression::Gzip) => Decoder::Gzip(flate2::bufread::MultiGzDecoder::new(input)),
+            Some(Compression::Zstd) => {
+                Decoder::Zstd(zstd::stream::read::Decoder::with_buffer(input)?)
+            }
+            Some(Compression::Xz) => Decoder::Xz(xz2::bufread::XzDecoder::new_multi_decoder(input)),
+            Some(Compression::Bzip2) => Decoder::Bzip2(bzip2::bufread::MultiBzDecoder::new(input)),
+        };
+        Ok(Self {
+            decoder,
+            compression,
+            position,
+        })
+    }
+
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// position in the input, which is the count of compressed bytes read so far
+    pub fn position(&self) -> ReadPosition {
+        self.position.clone()
+    }
+}
+
+impl<R: Read> Read for DecompressingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.decoder {
+            Decoder::Plain(reader) => reader.read(buf),
+            Decoder::Gzip(reader) => reader.read(buf),
+            Decoder::Zstd(reader) => reader.read(buf),
+            Decoder::Xz(reader) => reader.read(buf),
+            Decoder::Bzip2(reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::raw::BinaryByteSource, ByteSource};
+    use std::io::{Cursor, Write};
+
+    const CONTENT: &[u8] = b"first line\nsecond line\n\x00\x01\x02 binary tail";
+
+    fn compress(compression: Compression) -> Vec<u8> {
+        match compression {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(CONTENT).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::stream::encode_all(CONTENT, 0).unwrap(),
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(CONTENT).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(CONTENT).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        for compression in [
+            Compression::Gzip,
+            Compression::Zstd,
+            Compression::Xz,
+            Compression::Bzip2,
+        ] {
+            assert_eq!(
+                Compression::detect(&compress(compression)),
+                Some(compression)
+            );
+        }
+        assert_eq!(Compression::detect(CONTENT), None);
+        assert_eq!(Compression::detect(b"BZh is not bzip2"), None);
+        assert_eq!(Compression::detect(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_source() {
+        for compression in [
+            None,
+            Some(Compression::Gzip),
+            Some(Compression::Zstd),
+            Some(Compression::Xz),
+            Some(Compression::Bzip2),
+        ] {
+            let input = compression
+                .map(compress)
+                .unwrap_or_else(|| CONTENT.to_vec());
+            let input_len = input.len() as u64;
+            let reader = DecompressingReader::new(Cursor::new(input)).unwrap();
+            assert_eq!(reader.compression(), compression);
+            let position = reader.position();
+            let mut source = BinaryByteSource::new(reader);
+            let mut content = vec![];
+            while source.reload(None).await.unwrap().is_some() {
+                content.extend_from_slice(source.current_slice());
+                source.consume(source.len());
+            }
+            assert_eq!(content, CONTENT);
+            assert_eq!(position.get(), input_len);
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod archive;
+pub mod compressed;
 pub mod pcap;
 pub mod raw;
+pub mod rotating;
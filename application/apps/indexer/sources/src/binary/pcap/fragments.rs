@@ -0,0 +1,202 @@
+use etherparse::{InternetSlice, SlicedPacket, TcpHeaderSlice, TransportSlice, UdpHeaderSlice};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::Ipv4Addr,
+};
+
+/// incomplete packets kept at the same time; once exceeded, the fragments of the
+/// oldest packet are dropped
+const MAX_INCOMPLETE_PACKETS: usize = 1024;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// fragments belong to the same packet if source, destination, protocol and
+/// identification are equal
+type PacketKey = (Ipv4Addr, Ipv4Addr, u8, u16);
+
+#[derive(Debug, Default)]
+struct Incomplete {
+    /// offset of a fragment in the payload and its bytes
+    fragments: Vec<(usize, Vec<u8>)>,
+    /// size of the payload, known once the last fragment arrived
+    len: Option<usize>,
+}
+
+impl Incomplete {
+    /// the payload if all fragments arrived
+    fn payload(&mut self) -> Option<Vec<u8>> {
+        let len = self.len?;
+        self.fragments.sort_by_key(|(offset, _)| *offset);
+        let mut payload = Vec::with_capacity(len);
+        for (offset, bytes) in self.fragments.iter() {
+            if *offset > payload.len() {
+                return None;
+            }
+            let end = (offset + bytes.len()).min(len);
+            if end > payload.len() {
+                payload.extend_from_slice(&bytes[payload.len() - offset..end - offset]);
+            }
+        }
+        (payload.len() == len).then_some(payload)
+    }
+}
+
+pub(crate) enum Defragmented {
+    /// the packet wasn't fragmented
+    Unfragmented,
+    /// fragment of a packet, which isn't complete yet
+    Pending,
+    /// last missing fragment of a packet, the whole IP payload is provided
+    Complete(Vec<u8>),
+}
+
+/// Puts fragmented IPv4 packets together
+#[derive(Debug, Default)]
+pub(crate) struct Fragments {
+    incomplete: HashMap<PacketKey, Incomplete>,
+    /// incomplete packets in order of their first fragment
+    order: VecDeque<PacketKey>,
+}
+
+impl Fragments {
+    pub fn add(&mut self, packet: &SlicedPacket) -> Defragmented {
+        let header = match &packet.ip {
+            Some(InternetSlice::Ipv4(header, _)) => header,
+            _ => return Defragmented::Unfragmented,
+        };
+        if !header.more_fragments() && header.fragments_offset() == 0 {
+            return Defragmented::Unfragmented;
+        }
+        let key = (
+            header.source_addr(),
+            header.destination_addr(),
+            header.protocol(),
+            header.identification(),
+        );
+        if !self.incomplete.contains_key(&key) {
+            if self.order.len() >= MAX_INCOMPLETE_PACKETS {
+                if let Some(oldest) = self.order.pop_front() {
+                    warn!("Fragments of IP packet {oldest:?} dropped, packet is incomplete");
+                    self.incomplete.remove(&oldest);
+                }
+            }
+            self.order.push_back(key);
+        }
+        let incomplete = self.incomplete.entry(key).or_default();
+        let offset = header.fragments_offset() as usize * 8;
+        let bytes = ip_payload(packet);
+        if !header.more_fragments() {
+            incomplete.len = Some(offset + bytes.len());
+        }
+        incomplete.fragments.push((offset, bytes));
+        match incomplete.payload() {
+            Some(payload) => {
+                self.incomplete.remove(&key);
+                self.order.retain(|pending| *pending != key);
+                Defragmented::Complete(payload)
+            }
+            None => Defragmented::Pending,
+        }
+    }
+}
+
+/// the fragment carried by the packet, including the transport header in case it
+/// was parsed already
+fn ip_payload(packet: &SlicedPacket) -> Vec<u8> {
+    let header: &[u8] = match &packet.transport {
+        Some(TransportSlice::Udp(header)) => header.slice(),
+        Some(TransportSlice::Tcp(header)) => header.slice(),
+        _ => &[],
+    };
+    [header, packet.payload].concat()
+}
+
+/// the packet with the transport header and payload taken from the reassembled
+/// IP payload
+pub(crate) fn with_ip_payload<'a>(
+    packet: &SlicedPacket<'a>,
+    payload: &'a [u8],
+) -> SlicedPacket<'a> {
+    let protocol = match &packet.ip {
+        Some(InternetSlice::Ipv4(header, _)) => header.protocol(),
+        _ => 0,
+    };
+    let (transport, payload) = match protocol {
+        IP_PROTOCOL_UDP => match UdpHeaderSlice::from_slice(payload) {
+            Ok(header) => {
+                let end = (header.length() as usize).clamp(header.slice().len(), payload.len());
+                let payload = &payload[header.slice().len()..end];
+                (Some(TransportSlice::Udp(header)), payload)
+            }
+            Err(_) => (None, payload),
+        },
+        IP_PROTOCOL_TCP => match TcpHeaderSlice::from_slice(payload) {
+            Ok(header) => {
+                let payload = &payload[header.slice().len()..];
+                (Some(TransportSlice::Tcp(header)), payload)
+            }
+            Err(_) => (None, payload),
+        },
+        _ => (None, payload),
+    };
+    SlicedPacket {
+        transport,
+        payload,
+        ..packet.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(flags_and_offset: [u8; 2], payload: &[u8]) -> Vec<u8> {
+        let total_len = (20 + payload.len()) as u16;
+        let mut packet = vec![0x45, 0x00];
+        packet.extend_from_slice(&total_len.to_be_bytes());
+        // identification
+        packet.extend_from_slice(&[0x12, 0x34]);
+        packet.extend_from_slice(&flags_and_offset);
+        // ttl, protocol (UDP), checksum
+        packet.extend_from_slice(&[0x40, IP_PROTOCOL_UDP, 0x00, 0x00]);
+        packet.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn test_reassemble_udp_datagram() {
+        // UDP header (ports 30000 -> 30001, length 20) and the first 8 bytes
+        let first = fragment(
+            [0x20, 0x00],
+            &[
+                0x75, 0x30, 0x75, 0x31, 0x00, 0x14, 0x00, 0x00, b'a', b'b', b'c', b'd', b'e', b'f',
+                b'g', b'h',
+            ],
+        );
+        let last = fragment([0x00, 0x02], b"ijkl");
+        let mut fragments = Fragments::default();
+        let packet = SlicedPacket::from_ip(&last).expect("valid fragment");
+        assert!(matches!(fragments.add(&packet), Defragmented::Pending));
+        let packet = SlicedPacket::from_ip(&first).expect("valid fragment");
+        let payload = match fragments.add(&packet) {
+            Defragmented::Complete(payload) => payload,
+            _ => panic!("packet should be complete"),
+        };
+        let packet = with_ip_payload(&packet, &payload);
+        assert_eq!(packet.payload, b"abcdefghijkl");
+        match packet.transport {
+            Some(TransportSlice::Udp(header)) => assert_eq!(header.destination_port(), 30001),
+            _ => panic!("UDP header expected"),
+        }
+        let unfragmented = fragment(
+            [0x40, 0x00],
+            &[
+                0x75, 0x30, 0x75, 0x31, 0x00, 0x0c, 0x00, 0x00, b'i', b'j', b'k', b'l',
+            ],
+        );
+        let packet = SlicedPacket::from_ip(&unfragmented).expect("valid packet");
+        assert!(matches!(fragments.add(&packet), Defragmented::Unfragmented));
+    }
+}
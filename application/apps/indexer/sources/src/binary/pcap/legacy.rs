@@ -1,16 +1,25 @@
 use crate::{
-    binary::pcap::debug_block, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
-    TransportProtocol,
+    binary::pcap::{
+        debug_block,
+        fragments::{with_ip_payload, Defragmented, Fragments},
+        reassembly::{Connection, Streams},
+        slice_packet, tcp_segment,
+    },
+    ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
 };
 use async_trait::async_trait;
-use buf_redux::Buffer;
 use log::{debug, error, trace};
-use pcap_parser::{traits::PcapReaderIterator, LegacyPcapReader, PcapBlockOwned, PcapError};
+use pcap_parser::{
+    traits::PcapReaderIterator, LegacyPcapReader, Linktype, PcapBlockOwned, PcapError,
+};
 use std::io::Read;
 
 pub struct PcapLegacyByteSource<R: Read> {
     pcap_reader: LegacyPcapReader<R>,
-    buffer: Buffer,
+    streams: Streams,
+    fragments: Fragments,
+    /// link type given by the file header
+    link_type: Linktype,
     last_know_timestamp: Option<u64>,
     total: usize,
 }
@@ -20,11 +29,19 @@ impl<R: Read> PcapLegacyByteSource<R> {
         Ok(Self {
             pcap_reader: LegacyPcapReader::new(65536, reader)
                 .map_err(|e| SourceError::Setup(format!("{e}")))?,
-            buffer: Buffer::new(),
+            streams: Streams::default(),
+            fragments: Fragments::default(),
+            link_type: Linktype::ETHERNET,
             last_know_timestamp: None,
             total: 0,
         })
     }
+
+    /// TCP connections, which carried data so far. Both directions of a connection
+    /// are listed separately.
+    pub fn connections(&self) -> &[Connection] {
+        self.streams.connections()
+    }
 }
 
 #[async_trait]
@@ -33,116 +50,115 @@ impl<R: Read + Send + Sync> ByteSource for PcapLegacyByteSource<R> {
         &mut self,
         filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        let raw_data;
-        let mut consumed;
         let mut skipped = 0usize;
+        // packets not matching the filter are skipped
         loop {
-            match self.pcap_reader.next() {
-                Ok((bytes_read, block)) => {
-                    self.total += bytes_read;
-                    trace!(
-                        "PcapByteSource::reload, bytes_read: {} (total: {})",
-                        bytes_read,
-                        self.total
-                    );
-                    consumed = bytes_read;
-                    match block {
-                        PcapBlockOwned::LegacyHeader(ref hdr) => {
-                            println!("LegacyHeader {:?}", hdr);
-                            self.pcap_reader.consume(consumed);
-                            continue;
-                        }
-                        PcapBlockOwned::Legacy(ref b) => {
-                            println!("Legacy: {:?}", b);
-                            raw_data = &b.data[..b.origlen as usize];
-                            break;
-                        }
-                        other_type => {
-                            debug_block(other_type);
-                            skipped += consumed;
-                            debug!("skipped in total {} bytes", skipped);
-                            self.pcap_reader.consume(consumed);
-                            continue;
+            if let Some(reload_info) = self.streams.reload(skipped, false) {
+                return Ok(Some(reload_info));
+            }
+            let raw_data;
+            let mut consumed;
+            loop {
+                match self.pcap_reader.next() {
+                    Ok((bytes_read, block)) => {
+                        self.total += bytes_read;
+                        trace!(
+                            "PcapByteSource::reload, bytes_read: {} (total: {})",
+                            bytes_read,
+                            self.total
+                        );
+                        consumed = bytes_read;
+                        match block {
+                            PcapBlockOwned::LegacyHeader(ref hdr) => {
+                                println!("LegacyHeader {:?}", hdr);
+                                self.link_type = hdr.network;
+                                self.pcap_reader.consume(consumed);
+                                continue;
+                            }
+                            PcapBlockOwned::Legacy(ref b) => {
+                                println!("Legacy: {:?}", b);
+                                raw_data = &b.data[..b.origlen as usize];
+                                break;
+                            }
+                            other_type => {
+                                debug_block(other_type);
+                                skipped += consumed;
+                                debug!("skipped in total {} bytes", skipped);
+                                self.pcap_reader.consume(consumed);
+                                continue;
+                            }
                         }
                     }
+                    Err(PcapError::Eof) => {
+                        debug!("reloading from pcap file, EOF");
+                        return Ok(self.streams.reload(skipped, true));
+                    }
+                    Err(PcapError::Incomplete) => {
+                        trace!("reloading from pcap file, Incomplete");
+                        self.pcap_reader
+                            .refill()
+                            .expect("refill pcap reader failed");
+                        // continue;
+                    }
+                    Err(e) => {
+                        let m = format!("{e}");
+                        error!("reloading from pcap file, {}", m);
+                        return Err(SourceError::Unrecoverable(m));
+                    }
                 }
-                Err(PcapError::Eof) => {
-                    debug!("reloading from pcap file, EOF");
-                    return Ok(None);
+            }
+            let packet = match slice_packet(self.link_type, raw_data) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    debug!("packet skipped, cannot be sliced: {e}");
+                    skipped += consumed;
+                    self.pcap_reader.consume(consumed);
+                    continue;
                 }
-                Err(PcapError::Incomplete) => {
-                    trace!("reloading from pcap file, Incomplete");
-                    self.pcap_reader
-                        .refill()
-                        .expect("refill pcap reader failed");
-                    // continue;
+            };
+            let defragmented;
+            let packet = match self.fragments.add(&packet) {
+                Defragmented::Unfragmented => packet,
+                Defragmented::Pending => {
+                    trace!("IP fragment kept until the packet is complete");
+                    skipped += consumed;
+                    self.pcap_reader.consume(consumed);
+                    continue;
                 }
-                Err(e) => {
-                    let m = format!("{e}");
-                    error!("reloading from pcap file, {}", m);
-                    return Err(SourceError::Unrecoverable(m));
+                Defragmented::Complete(payload) => {
+                    defragmented = payload;
+                    with_ip_payload(&packet, &defragmented)
                 }
+            };
+            if filter.is_some_and(|filter| !filter.matches_packet(&packet, None)) {
+                trace!("packet skipped by filter");
+                skipped += consumed;
+                self.pcap_reader.consume(consumed);
+                continue;
             }
+            skipped += consumed.saturating_sub(packet.payload.len());
+            self.streams.add(
+                None,
+                tcp_segment(&packet),
+                packet.payload,
+                self.last_know_timestamp,
+            );
+            // bytes are copied into their stream and can be dropped by pcap reader
+            trace!("consume {} processed bytes", consumed);
+            self.pcap_reader.consume(consumed);
         }
-        let res = match etherparse::SlicedPacket::from_ethernet(raw_data) {
-            Ok(value) => {
-                skipped += consumed - value.payload.len();
-                match (value.transport, filter) {
-                    (
-                        Some(actual),
-                        Some(SourceFilter {
-                            transport: Some(wanted),
-                        }),
-                    ) => {
-                        let actual_tp: TransportProtocol = actual.into();
-                        let received_bytes = self.buffer.copy_from_slice(value.payload);
-                        if actual_tp == *wanted {
-                            Ok(Some(ReloadInfo::new(
-                                received_bytes,
-                                received_bytes,
-                                skipped,
-                                self.last_know_timestamp,
-                            )))
-                        } else {
-                            Ok(Some(ReloadInfo::new(
-                                0,
-                                0,
-                                value.payload.len() + skipped,
-                                self.last_know_timestamp,
-                            )))
-                        }
-                    }
-                    _ => {
-                        let copied = self.buffer.copy_from_slice(value.payload);
-                        Ok(Some(ReloadInfo::new(
-                            copied,
-                            copied,
-                            skipped,
-                            self.last_know_timestamp,
-                        )))
-                    }
-                }
-            }
-            Err(e) => Err(SourceError::Unrecoverable(format!(
-                "error trying to extract data from ethernet frame: {e}"
-            ))),
-        };
-        // bytes are copied into buffer and can be dropped by pcap reader
-        trace!("consume {} processed bytes", consumed);
-        self.pcap_reader.consume(consumed);
-        res
     }
 
     fn current_slice(&self) -> &[u8] {
-        self.buffer.buf()
+        self.streams.current()
     }
 
     fn consume(&mut self, offset: usize) {
-        self.buffer.consume(offset);
+        self.streams.consume(offset);
     }
 
     fn len(&self) -> usize {
-        self.buffer.len()
+        self.streams.current().len()
     }
 }
 
@@ -152,10 +168,8 @@ mod tests {
 
     use crate::{binary::pcap::legacy::PcapLegacyByteSource, ByteSource};
 
-    #[tokio::test]
-    async fn test_read_one_message_from_pcap() {
-        let _ = env_logger::try_init();
-        let sample_pcap_data = vec![
+    fn sample_pcap_data() -> Vec<u8> {
+        vec![
             0xd4, 0xc3, 0xb2, 0xa1, // Magic Number (4 bytes) = d4 c3 b2 a1
             0x02, 0x00, // Version Major (2 bytes) = 02 00
             0x04, 0x00, // Version Minor (2 bytes) = 04 00
@@ -177,7 +191,13 @@ mod tests {
             0x02, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x10,
             0x01, 0x03, 0x00, 0x01, 0x01, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x0c, 0x00, 0x09, 0x04, 0x00, 0xac, 0x16, 0x0c, 0x4f, 0x00, 0x11, 0x75, 0x30,
-        ];
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_read_one_message_from_pcap() {
+        let _ = env_logger::try_init();
+        let sample_pcap_data = sample_pcap_data();
         let udp_payload = &sample_pcap_data[82..=137];
         let pcap_file = std::io::Cursor::new(&sample_pcap_data);
 
@@ -189,4 +209,27 @@ mod tests {
         assert_eq!(slice.len(), 56);
         assert_eq!(slice, udp_payload);
     }
+    #[tokio::test]
+    async fn test_read_linux_cooked_capture() {
+        let _ = env_logger::try_init();
+        let ethernet = sample_pcap_data();
+        let mut sample_pcap_data = ethernet[..24].to_vec();
+        // LINKTYPE_LINUX_SLL
+        sample_pcap_data[20] = 0x71;
+        // packet header with captured and original length of 100 bytes
+        sample_pcap_data.extend_from_slice(&ethernet[24..32]);
+        sample_pcap_data.extend_from_slice(&[0x64, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00]);
+        // packet type, ARPHRD_ETHER, address length, address, protocol (IPv4)
+        sample_pcap_data.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0xb8, 0x27, 0xeb, 0x98, 0x94, 0xfa, 0x00, 0x00,
+            0x08, 0x00,
+        ]);
+        // IP packet
+        sample_pcap_data.extend_from_slice(&ethernet[54..]);
+        let pcap_file = std::io::Cursor::new(&sample_pcap_data);
+
+        let mut source = PcapLegacyByteSource::new(pcap_file).expect("cannot create source");
+        source.reload(None).await.expect("reload should work");
+        assert_eq!(source.current_slice(), &ethernet[82..]);
+    }
 }
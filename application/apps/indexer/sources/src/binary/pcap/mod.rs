@@ -1,7 +1,131 @@
-use pcap_parser::PcapBlockOwned;
+use crate::{SourceFilter, TransportProtocol};
+use etherparse::{InternetSlice, SlicedPacket, TransportSlice, VlanSlice};
+use pcap_parser::{Linktype, PcapBlockOwned};
+use reassembly::{Connection, Segment};
+use std::net::{IpAddr, SocketAddr};
 
+pub(crate) mod fragments;
 pub mod legacy;
 pub mod ng;
+pub mod reassembly;
+
+/// length of the Linux cooked capture header (SLL)
+const SLL_HEADER_LEN: usize = 16;
+/// length of the Linux cooked capture header, version 2 (SLL2)
+const SLL2_HEADER_LEN: usize = 20;
+/// length of the header of BSD loopback captures, which is the address family
+const NULL_HEADER_LEN: usize = 4;
+
+/// slices a captured packet according to the link type of the interface it was
+/// captured on
+fn slice_packet(link_type: Linktype, data: &[u8]) -> Result<SlicedPacket, String> {
+    let too_short = || format!("packet of {} bytes too short for {link_type:?}", data.len());
+    match link_type {
+        Linktype::ETHERNET => SlicedPacket::from_ethernet(data).map_err(|e| e.to_string()),
+        Linktype::LINUX_SLL => {
+            if data.len() < SLL_HEADER_LEN {
+                return Err(too_short());
+            }
+            let protocol = u16::from_be_bytes([data[14], data[15]]);
+            SlicedPacket::from_ether_type(protocol, &data[SLL_HEADER_LEN..])
+                .map_err(|e| e.to_string())
+        }
+        Linktype::LINUX_SLL2 => {
+            if data.len() < SLL2_HEADER_LEN {
+                return Err(too_short());
+            }
+            let protocol = u16::from_be_bytes([data[0], data[1]]);
+            SlicedPacket::from_ether_type(protocol, &data[SLL2_HEADER_LEN..])
+                .map_err(|e| e.to_string())
+        }
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => {
+            SlicedPacket::from_ip(data).map_err(|e| e.to_string())
+        }
+        Linktype::NULL | Linktype::LOOP => {
+            if data.len() < NULL_HEADER_LEN {
+                return Err(too_short());
+            }
+            SlicedPacket::from_ip(&data[NULL_HEADER_LEN..]).map_err(|e| e.to_string())
+        }
+        _ => Err(format!("link type {link_type:?} is not supported")),
+    }
+}
+
+fn addresses(ip: &InternetSlice) -> (IpAddr, IpAddr) {
+    match ip {
+        InternetSlice::Ipv4(header, ..) => (
+            IpAddr::V4(header.source_addr()),
+            IpAddr::V4(header.destination_addr()),
+        ),
+        InternetSlice::Ipv6(header, ..) => (
+            IpAddr::V6(header.source_addr()),
+            IpAddr::V6(header.destination_addr()),
+        ),
+    }
+}
+
+/// connection and header fields of TCP packets, which are needed to reassemble
+/// the stream of the connection
+fn tcp_segment(packet: &SlicedPacket) -> Option<(Connection, Segment)> {
+    match (&packet.ip, &packet.transport) {
+        (Some(ip), Some(TransportSlice::Tcp(header))) => {
+            let (source, destination) = addresses(ip);
+            Some((
+                Connection {
+                    source: SocketAddr::new(source, header.source_port()),
+                    destination: SocketAddr::new(destination, header.destination_port()),
+                },
+                Segment {
+                    seq: header.sequence_number(),
+                    syn: header.syn(),
+                    fin: header.fin(),
+                    rst: header.rst(),
+                },
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl SourceFilter {
+    /// checks a captured packet, `interface_id` is only known for pcapng files
+    pub(crate) fn matches_packet(&self, packet: &SlicedPacket, interface_id: Option<u32>) -> bool {
+        let (source_ip, destination_ip) = match packet.ip.as_ref().map(addresses) {
+            Some((source, destination)) => (Some(source), Some(destination)),
+            None => (None, None),
+        };
+        let (source_port, destination_port) = match &packet.transport {
+            Some(TransportSlice::Udp(header)) => {
+                (Some(header.source_port()), Some(header.destination_port()))
+            }
+            Some(TransportSlice::Tcp(header)) => {
+                (Some(header.source_port()), Some(header.destination_port()))
+            }
+            _ => (None, None),
+        };
+        let vlan_ids = match &packet.vlan {
+            Some(VlanSlice::SingleVlan(header)) => vec![header.vlan_identifier()],
+            Some(VlanSlice::DoubleVlan(header)) => vec![
+                header.outer().vlan_identifier(),
+                header.inner().vlan_identifier(),
+            ],
+            None => vec![],
+        };
+        Self::wanted(
+            &self.transport,
+            packet.transport.clone().map(TransportProtocol::from),
+        ) && Self::wanted(&self.source_ip, source_ip)
+            && Self::wanted(&self.destination_ip, destination_ip)
+            && Self::wanted(&self.source_port, source_port)
+            && Self::wanted(&self.destination_port, destination_port)
+            && (self.port.is_none() || source_port == self.port || destination_port == self.port)
+            && match self.vlan_id {
+                Some(vlan_id) => vlan_ids.contains(&vlan_id),
+                None => true,
+            }
+            && (interface_id.is_none() || Self::wanted(&self.interface_id, interface_id))
+    }
+}
 
 fn debug_block(b: PcapBlockOwned) {
     match b {
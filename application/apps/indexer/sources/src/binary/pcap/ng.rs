@@ -1,16 +1,87 @@
 use crate::{
-    binary::pcap::debug_block, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
-    TransportProtocol,
+    binary::pcap::{
+        debug_block,
+        fragments::{with_ip_payload, Defragmented, Fragments},
+        reassembly::{Connection, Streams},
+        slice_packet, tcp_segment,
+    },
+    ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
 };
 use async_trait::async_trait;
-use buf_redux::Buffer;
-use log::{debug, error, trace};
-use pcap_parser::{traits::PcapReaderIterator, PcapBlockOwned, PcapError, PcapNGReader};
+use log::{debug, error, trace, warn};
+use pcap_parser::{
+    traits::PcapReaderIterator, Block, InterfaceDescriptionBlock, Linktype, PcapBlockOwned,
+    PcapError, PcapNGReader,
+};
 use std::io::Read;
 
+/// option code of the name of an interface
+const IF_NAME: u16 = 2;
+/// timestamps are given in microseconds, if the resolution isn't defined
+const DEFAULT_TS_UNITS_PER_SECOND: u64 = 1_000_000;
+
+/// Capturing interface as described by an Interface Description Block
+#[derive(Debug)]
+struct Interface {
+    link_type: Linktype,
+    /// resolution of the timestamps of packets (`if_tsresol`)
+    ts_units_per_second: u64,
+    /// seconds to add to the timestamps of packets (`if_tsoffset`)
+    ts_offset: i64,
+    name: Option<String>,
+}
+
+impl Interface {
+    fn new(idb: &InterfaceDescriptionBlock) -> Self {
+        let name = idb
+            .options
+            .iter()
+            .find(|option| option.code.0 == IF_NAME)
+            .map(|option| {
+                String::from_utf8_lossy(&option.value[..])
+                    .trim_end_matches('\0')
+                    .to_owned()
+            })
+            .filter(|name| !name.is_empty());
+        Self {
+            link_type: idb.linktype,
+            ts_units_per_second: ts_units_per_second(idb.if_tsresol),
+            ts_offset: idb.if_tsoffset,
+            name,
+        }
+    }
+
+    /// timestamp of a packet in milliseconds since epoch
+    fn timestamp(&self, ts_high: u32, ts_low: u32) -> u64 {
+        let ts = (ts_high as u64) << 32 | ts_low as u64;
+        let ms =
+            ts as i128 * 1000 / self.ts_units_per_second as i128 + self.ts_offset as i128 * 1000;
+        ms.clamp(0, u64::MAX as i128) as u64
+    }
+}
+
+/// The most significant bit of `if_tsresol` tells if the remaining bits are a
+/// negative power of 10 or of 2.
+fn ts_units_per_second(if_tsresol: u8) -> u64 {
+    let exponent = (if_tsresol & 0x7f) as u32;
+    if if_tsresol & 0x80 == 0 {
+        10u64.checked_pow(exponent)
+    } else {
+        2u64.checked_pow(exponent)
+    }
+    .filter(|units| *units > 0)
+    .unwrap_or_else(|| {
+        warn!("Invalid timestamp resolution {if_tsresol:#x}, microseconds are used");
+        DEFAULT_TS_UNITS_PER_SECOND
+    })
+}
+
 pub struct PcapngByteSource<R: Read> {
     pcapng_reader: PcapNGReader<R>,
-    buffer: Buffer,
+    streams: Streams,
+    fragments: Fragments,
+    /// interfaces of the current section, by interface id
+    interfaces: Vec<Interface>,
     last_know_timestamp: Option<u64>,
     total: usize,
 }
@@ -20,11 +91,19 @@ impl<R: Read> PcapngByteSource<R> {
         Ok(Self {
             pcapng_reader: PcapNGReader::new(65536, reader)
                 .map_err(|e| SourceError::Setup(format!("{e}")))?,
-            buffer: Buffer::new(),
+            streams: Streams::default(),
+            fragments: Fragments::default(),
+            interfaces: Vec::new(),
             last_know_timestamp: None,
             total: 0,
         })
     }
+
+    /// TCP connections, which carried data so far. Both directions of a connection
+    /// are listed separately.
+    pub fn connections(&self) -> &[Connection] {
+        self.streams.connections()
+    }
 }
 
 #[async_trait]
@@ -33,130 +112,160 @@ impl<R: Read + Send + Sync> ByteSource for PcapngByteSource<R> {
         &mut self,
         filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        let raw_data;
-        let mut consumed;
         let mut skipped = 0usize;
+        // packets not matching the filter are skipped
         loop {
-            match self.pcapng_reader.next() {
-                Ok((bytes_read, block)) => {
-                    self.total += bytes_read;
-                    trace!(
-                        "PcapngByteSource::reload, bytes_read: {} (total: {})",
-                        bytes_read,
-                        self.total
-                    );
-                    consumed = bytes_read;
-                    match block {
-                        PcapBlockOwned::NG(pcap_parser::Block::EnhancedPacket(ref epb)) => {
-                            trace!("Enhanced package");
-                            let ts_us: u64 = (epb.ts_high as u64) << 32 | epb.ts_low as u64;
-                            self.last_know_timestamp = Some(ts_us / 1000);
-                            raw_data = &epb.data[..epb.caplen as usize];
-                            break;
-                        }
-                        PcapBlockOwned::NG(pcap_parser::Block::SimplePacket(ref spb)) => {
-                            trace!("SimplePacket");
-                            raw_data = &spb.data[..spb.origlen as usize];
-                            break;
-                        }
-                        other_type => {
-                            debug_block(other_type);
-                            skipped += consumed;
-                            debug!("skipped in total {} bytes", skipped);
-                            self.pcapng_reader.consume(consumed);
-                            continue;
+            if let Some(reload_info) = self.streams.reload(skipped, false) {
+                return Ok(Some(reload_info));
+            }
+            let raw_data;
+            let mut consumed;
+            let interface_id;
+            loop {
+                match self.pcapng_reader.next() {
+                    Ok((bytes_read, block)) => {
+                        self.total += bytes_read;
+                        trace!(
+                            "PcapngByteSource::reload, bytes_read: {} (total: {})",
+                            bytes_read,
+                            self.total
+                        );
+                        consumed = bytes_read;
+                        match block {
+                            PcapBlockOwned::NG(pcap_parser::Block::EnhancedPacket(ref epb)) => {
+                                trace!("Enhanced package");
+                                self.last_know_timestamp =
+                                    Some(match self.interfaces.get(epb.if_id as usize) {
+                                        Some(interface) => {
+                                            interface.timestamp(epb.ts_high, epb.ts_low)
+                                        }
+                                        None => {
+                                            ((epb.ts_high as u64) << 32 | epb.ts_low as u64)
+                                                / (DEFAULT_TS_UNITS_PER_SECOND / 1000)
+                                        }
+                                    });
+                                interface_id = Some(epb.if_id);
+                                raw_data = &epb.data[..epb.caplen as usize];
+                                break;
+                            }
+                            PcapBlockOwned::NG(pcap_parser::Block::SimplePacket(ref spb)) => {
+                                trace!("SimplePacket");
+                                // simple packets belong to the first interface
+                                interface_id = Some(0);
+                                raw_data = &spb.data[..spb.origlen as usize];
+                                break;
+                            }
+                            other_type => {
+                                match &other_type {
+                                    // interface ids are counted per section
+                                    PcapBlockOwned::NG(Block::SectionHeader(_)) => {
+                                        self.interfaces.clear()
+                                    }
+                                    PcapBlockOwned::NG(Block::InterfaceDescription(idb)) => {
+                                        self.interfaces.push(Interface::new(idb))
+                                    }
+                                    _ => {}
+                                }
+                                debug_block(other_type);
+                                skipped += consumed;
+                                debug!("skipped in total {} bytes", skipped);
+                                self.pcapng_reader.consume(consumed);
+                                continue;
+                            }
                         }
                     }
+                    Err(PcapError::Eof) => {
+                        debug!("reloading from pcap file, EOF");
+                        return Ok(self.streams.reload(skipped, true));
+                    }
+                    Err(PcapError::Incomplete) => {
+                        trace!("reloading from pcap file, Incomplete");
+                        self.pcapng_reader
+                            .refill()
+                            .expect("refill pcapng reader failed");
+                        // continue;
+                    }
+                    Err(e) => {
+                        let m = format!("{e}");
+                        error!("reloading from pcap file, {}", m);
+                        return Err(SourceError::Unrecoverable(m));
+                    }
                 }
-                Err(PcapError::Eof) => {
-                    debug!("reloading from pcap file, EOF");
-                    return Ok(None);
+            }
+            let link_type = interface_id
+                .and_then(|id| self.interfaces.get(id as usize))
+                .map_or(Linktype::ETHERNET, |interface| interface.link_type);
+            let packet = match slice_packet(link_type, raw_data) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    debug!("packet skipped, cannot be sliced: {e}");
+                    skipped += consumed;
+                    self.pcapng_reader.consume(consumed);
+                    continue;
                 }
-                Err(PcapError::Incomplete) => {
-                    trace!("reloading from pcap file, Incomplete");
-                    self.pcapng_reader
-                        .refill()
-                        .expect("refill pcapng reader failed");
-                    // continue;
+            };
+            let defragmented;
+            let packet = match self.fragments.add(&packet) {
+                Defragmented::Unfragmented => packet,
+                Defragmented::Pending => {
+                    trace!("IP fragment kept until the packet is complete");
+                    skipped += consumed;
+                    self.pcapng_reader.consume(consumed);
+                    continue;
                 }
-                Err(e) => {
-                    let m = format!("{e}");
-                    error!("reloading from pcap file, {}", m);
-                    return Err(SourceError::Unrecoverable(m));
+                Defragmented::Complete(payload) => {
+                    defragmented = payload;
+                    with_ip_payload(&packet, &defragmented)
                 }
+            };
+            if filter.is_some_and(|filter| !filter.matches_packet(&packet, interface_id)) {
+                trace!("packet skipped by filter");
+                skipped += consumed;
+                self.pcapng_reader.consume(consumed);
+                continue;
             }
+            skipped += consumed.saturating_sub(packet.payload.len());
+            self.streams.add(
+                interface_id,
+                tcp_segment(&packet),
+                packet.payload,
+                self.last_know_timestamp,
+            );
+            // bytes are copied into their stream and can be dropped by pcap reader
+            trace!("consume {} processed bytes", consumed);
+            self.pcapng_reader.consume(consumed);
         }
-        let res = match etherparse::SlicedPacket::from_ethernet(raw_data) {
-            Ok(value) => {
-                skipped += consumed - value.payload.len();
-                match (value.transport, filter) {
-                    (
-                        Some(actual),
-                        Some(SourceFilter {
-                            transport: Some(wanted),
-                        }),
-                    ) => {
-                        let actual_tp: TransportProtocol = actual.into();
-                        let received_bytes = self.buffer.copy_from_slice(value.payload);
-                        if actual_tp == *wanted {
-                            Ok(Some(ReloadInfo::new(
-                                received_bytes,
-                                received_bytes,
-                                skipped,
-                                self.last_know_timestamp,
-                            )))
-                        } else {
-                            Ok(Some(ReloadInfo::new(
-                                0,
-                                0,
-                                value.payload.len() + skipped,
-                                self.last_know_timestamp,
-                            )))
-                        }
-                    }
-                    _ => {
-                        let copied = self.buffer.copy_from_slice(value.payload);
-                        Ok(Some(ReloadInfo::new(
-                            copied,
-                            copied,
-                            skipped,
-                            self.last_know_timestamp,
-                        )))
-                    }
-                }
-            }
-            Err(e) => Err(SourceError::Unrecoverable(format!(
-                "error trying to extract data from ethernet frame: {e}"
-            ))),
-        };
-        // bytes are copied into buffer and can be dropped by pcap reader
-        trace!("consume {} processed bytes", consumed);
-        self.pcapng_reader.consume(consumed);
-        res
     }
 
     fn current_slice(&self) -> &[u8] {
-        self.buffer.buf()
+        self.streams.current()
     }
 
     fn consume(&mut self, offset: usize) {
-        self.buffer.consume(offset);
+        self.streams.consume(offset);
     }
 
     fn len(&self) -> usize {
-        self.buffer.len()
+        self.streams.current().len()
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.streams
+            .interface()
+            .and_then(|id| self.interfaces.get(id as usize))
+            .and_then(|interface| interface.name.as_deref())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{binary::pcap::ng::PcapngByteSource, ByteSource};
+    use super::{ts_units_per_second, Interface};
+    use crate::{binary::pcap::ng::PcapngByteSource, ByteSource, SourceFilter, TransportProtocol};
     use env_logger;
+    use pcap_parser::Linktype;
 
-    #[tokio::test]
-    async fn test_read_one_message_from_pcapng() {
-        let _ = env_logger::try_init();
-        let sample_pcapng_data = vec![
+    fn sample_pcapng_data() -> Vec<u8> {
+        vec![
             /*0*/
             // section header block
             /* blocktype */ 0x0a, 0x0d,
@@ -196,16 +305,143 @@ mod tests {
             // --- end of ethernet packet ---------------------------
             // --- start of pcapng end ---------------------------
             0x00, 0x00, 0x84, 0x00, 0x00, 0x00,
-        ];
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_read_one_message_from_pcapng() {
+        let _ = env_logger::try_init();
+        let sample_pcapng_data = sample_pcapng_data();
         let udp_payload = &sample_pcapng_data[118..=173];
         let pcapng_file = std::io::Cursor::new(&sample_pcapng_data);
 
         let mut source = PcapngByteSource::new(pcapng_file).expect("cannot create source");
         let reload_info = source.reload(None).await.expect("reload should work");
         println!("reload_info: {:?}", reload_info);
+        // timestamp in microseconds, as no resolution is given
+        assert_eq!(
+            reload_info.and_then(|info| info.last_known_ts),
+            Some(1_619_531_243_294)
+        );
+        assert_eq!(source.origin(), None);
         let slice = source.current_slice();
         println!("slice: {:x?}", slice);
         assert_eq!(slice.len(), 56);
         assert_eq!(slice, udp_payload);
     }
+
+    #[tokio::test]
+    async fn test_read_interface_options_from_pcapng() {
+        let _ = env_logger::try_init();
+        let sample_pcapng_data = sample_pcapng_data();
+        let mut data = sample_pcapng_data[..28].to_vec();
+        data.extend_from_slice(&[
+            0x01, 0x00, 0x00, 0x00, /* blocktype */
+            0x28, 0x00, 0x00, 0x00, /* len */
+            0x01, 0x00, /* LINKTYPE_ETHERNET */
+            0x00, 0x00, /* reserved */
+            0x00, 0x00, 0x04, 0x00, /* snap-len */
+            0x02, 0x00, 0x04, 0x00, b'e', b't', b'h', b'0', /* if_name */
+            0x09, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, /* if_tsresol (ns) */
+            0x00, 0x00, 0x00, 0x00, /* opt_endofopt */
+            0x28, 0x00, 0x00, 0x00, // ---
+        ]);
+        data.extend_from_slice(&sample_pcapng_data[48..]);
+        let pcapng_file = std::io::Cursor::new(&data);
+
+        let mut source = PcapngByteSource::new(pcapng_file).expect("cannot create source");
+        let reload_info = source.reload(None).await.expect("reload should work");
+        assert_eq!(
+            reload_info.and_then(|info| info.last_known_ts),
+            Some(1_619_531_243)
+        );
+        assert_eq!(source.origin(), Some("eth0"));
+        assert_eq!(source.len(), 56);
+    }
+
+    #[test]
+    fn test_timestamp_resolution_and_offset() {
+        assert_eq!(ts_units_per_second(6), 1_000_000);
+        assert_eq!(ts_units_per_second(9), 1_000_000_000);
+        assert_eq!(ts_units_per_second(0x8a), 1024);
+        assert_eq!(ts_units_per_second(0x7f), 1_000_000);
+        let mut interface = Interface {
+            link_type: Linktype::ETHERNET,
+            ts_units_per_second: 1_000_000_000,
+            ts_offset: 0,
+            name: None,
+        };
+        let ts: u64 = 1_619_531_243_294_886_123;
+        assert_eq!(
+            interface.timestamp((ts >> 32) as u32, ts as u32),
+            1_619_531_243_294
+        );
+        interface.ts_offset = -3600;
+        assert_eq!(
+            interface.timestamp((ts >> 32) as u32, ts as u32),
+            1_619_527_643_294
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_packets_from_pcapng() {
+        let _ = env_logger::try_init();
+        let sample_pcapng_data = sample_pcapng_data();
+        for (filter, passed) in [
+            (
+                SourceFilter {
+                    destination_port: Some(50000),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (
+                SourceFilter {
+                    transport: Some(TransportProtocol::UDP),
+                    source_ip: Some("172.22.12.79".parse().unwrap()),
+                    destination_ip: Some("172.22.12.80".parse().unwrap()),
+                    interface_id: Some(0),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (
+                SourceFilter {
+                    transport: Some(TransportProtocol::TCP),
+                    ..Default::default()
+                },
+                false,
+            ),
+            (
+                SourceFilter {
+                    port: Some(3490),
+                    ..Default::default()
+                },
+                false,
+            ),
+            (
+                SourceFilter {
+                    vlan_id: Some(1),
+                    ..Default::default()
+                },
+                false,
+            ),
+            (
+                SourceFilter {
+                    interface_id: Some(1),
+                    ..Default::default()
+                },
+                false,
+            ),
+        ] {
+            let pcapng_file = std::io::Cursor::new(&sample_pcapng_data);
+            let mut source = PcapngByteSource::new(pcapng_file).expect("cannot create source");
+            let reload_info = source
+                .reload(Some(&filter))
+                .await
+                .expect("reload should work");
+            assert_eq!(reload_info.is_some(), passed, "{filter:?}");
+            assert_eq!(source.len(), if passed { 56 } else { 0 });
+        }
+    }
 }
@@ -0,0 +1,427 @@
+use crate::{ReloadInfo, DEFAULT_READER_CAPACITY};
+use buf_redux::Buffer;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::SocketAddr,
+};
+
+/// out of order segments kept while waiting for a missing segment; once exceeded,
+/// the missing bytes are taken as not captured and skipped
+const MAX_PENDING_SEGMENTS: usize = 256;
+/// bytes of other streams kept while waiting for the rest of an incomplete message
+/// of the stream being parsed; once exceeded, the incomplete message is dropped
+const MAX_WAITING_BYTES: usize = DEFAULT_READER_CAPACITY;
+
+/// One direction of a TCP connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Connection {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.source, self.destination)
+    }
+}
+
+/// header fields of a TCP segment needed to put it in order
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Segment {
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+}
+
+/// sequence numbers wrap around, `a` is before `b` if it's less than half of the
+/// number space behind
+fn is_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+/// Puts the segments of one direction of a TCP connection in order. Retransmitted
+/// bytes are dropped, segments arriving too early are kept until the missing
+/// bytes arrive.
+#[derive(Debug, Default)]
+struct Sequencer {
+    /// sequence number of the next expected byte
+    next: Option<u32>,
+    pending: Vec<(u32, Vec<u8>)>,
+    closed: bool,
+}
+
+impl Sequencer {
+    /// appends the bytes, which are in order now, to `out` and returns their count
+    fn push(&mut self, segment: &Segment, payload: &[u8], out: &mut Buffer) -> usize {
+        let mut seq = segment.seq;
+        if segment.syn {
+            // the connection is (re)established, data starts after the SYN
+            seq = seq.wrapping_add(1);
+            self.next = Some(seq);
+            self.pending.clear();
+            self.closed = false;
+        }
+        if segment.fin || segment.rst {
+            self.closed = true;
+        }
+        // capture might have started in the middle of the connection
+        let next = *self.next.get_or_insert(seq);
+        if payload.is_empty() {
+            return 0;
+        }
+        if !is_before(next, seq) {
+            return self.append(seq, payload, out) + self.drain(out);
+        }
+        if !self
+            .pending
+            .iter()
+            .any(|(pending, bytes)| *pending == seq && bytes.len() >= payload.len())
+        {
+            self.pending.push((seq, payload.to_vec()));
+        }
+        if self.pending.len() <= MAX_PENDING_SEGMENTS {
+            return 0;
+        }
+        // the missing bytes weren't captured, continue with the earliest segment
+        if let Some(earliest) = self
+            .pending
+            .iter()
+            .map(|(pending, _)| *pending)
+            .min_by_key(|pending| pending.wrapping_sub(next))
+        {
+            warn!(
+                "TCP segments missing, {} bytes skipped",
+                earliest.wrapping_sub(next)
+            );
+            self.next = Some(earliest);
+        }
+        self.drain(out)
+    }
+
+    /// appends the bytes of a segment, which doesn't start after the next expected
+    /// byte, without those received already
+    fn append(&mut self, seq: u32, payload: &[u8], out: &mut Buffer) -> usize {
+        let next = self.next.unwrap_or(seq);
+        let received = next.wrapping_sub(seq) as usize;
+        if received >= payload.len() {
+            trace!("retransmitted TCP segment dropped");
+            return 0;
+        }
+        let bytes = &payload[received..];
+        out.reserve(bytes.len());
+        let copied = out.copy_from_slice(bytes);
+        self.next = Some(next.wrapping_add(copied as u32));
+        copied
+    }
+
+    /// appends pending segments, which are in order now
+    fn drain(&mut self, out: &mut Buffer) -> usize {
+        let mut appended = 0;
+        while let Some(index) = self.next.and_then(|next| {
+            self.pending
+                .iter()
+                .position(|(pending, _)| !is_before(next, *pending))
+        }) {
+            let (seq, bytes) = self.pending.swap_remove(index);
+            appended += self.append(seq, &bytes, out);
+        }
+        appended
+    }
+
+    /// no more bytes are expected
+    fn is_finished(&self) -> bool {
+        self.closed && self.pending.is_empty()
+    }
+}
+
+/// Streams are kept per capturing interface. TCP connections are parsed by their
+/// direction, all other packets in order of their capture
+type StreamKey = (Option<u32>, Option<Connection>);
+
+#[derive(Debug)]
+struct Stream {
+    sequencer: Sequencer,
+    buffer: Buffer,
+    last_known_ts: Option<u64>,
+    /// the connection carried data already
+    listed: bool,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Self {
+            sequencer: Sequencer::default(),
+            buffer: Buffer::new(),
+            last_known_ts: None,
+            listed: false,
+        }
+    }
+}
+
+/// Splits the payload of captured packets into streams, so that the bytes of
+/// different TCP connections don't get mixed up and are parsed in order. The
+/// parser gets the bytes of one stream at a time and continues with another one
+/// as soon as all bytes are parsed. Until then, bytes of other streams are kept.
+#[derive(Debug, Default)]
+pub(crate) struct Streams {
+    streams: HashMap<StreamKey, Stream>,
+    /// TCP connections, which carried data, in order of their first data
+    connections: Vec<Connection>,
+    /// streams with bytes to parse, in order of their arrival
+    waiting: VecDeque<StreamKey>,
+    /// stream, which is parsed currently
+    active: StreamKey,
+    /// bytes of the active stream, which were provided and aren't consumed yet
+    provided: usize,
+    /// bytes of the active stream were consumed since they were provided last
+    consumed: bool,
+    /// bytes of incomplete messages, which were dropped
+    dropped: usize,
+}
+
+impl Streams {
+    /// adds the payload of a packet to its stream, which is given by the interface
+    /// it was captured on and its TCP connection, if any
+    pub fn add(
+        &mut self,
+        interface: Option<u32>,
+        tcp: Option<(Connection, Segment)>,
+        payload: &[u8],
+        ts: Option<u64>,
+    ) {
+        let key = (interface, tcp.map(|(connection, _)| connection));
+        let stream = self.streams.entry(key).or_default();
+        let added = match tcp {
+            Some((_, segment)) => stream.sequencer.push(&segment, payload, &mut stream.buffer),
+            None => {
+                stream.buffer.reserve(payload.len());
+                stream.buffer.copy_from_slice(payload)
+            }
+        };
+        if added == 0 {
+            return;
+        }
+        if ts.is_some() {
+            stream.last_known_ts = ts;
+        }
+        if let Some(connection) = key.1.filter(|_| !stream.listed) {
+            stream.listed = true;
+            // a connection might be captured on several interfaces
+            if !self.connections.contains(&connection) {
+                self.connections.push(connection);
+            }
+        }
+        // bytes of the stream being parsed are provided with the next reload
+        if (key != self.active || self.provided == 0) && !self.waiting.contains(&key) {
+            self.waiting.push_back(key);
+        }
+    }
+
+    /// Provides the bytes to parse next, `None` if more packets are needed. If
+    /// there are no more packets (`eof`), an incomplete message is dropped in favour
+    /// of other streams.
+    pub fn reload(&mut self, skipped: usize, eof: bool) -> Option<ReloadInfo> {
+        let available = self.current().len();
+        if available > 0 && (self.provided > 0 || self.consumed) {
+            if available > self.provided || self.consumed {
+                return Some(self.provide(available - self.provided, skipped));
+            }
+            // the parser needs more bytes of the active stream
+            let finished = self
+                .streams
+                .get(&self.active)
+                .is_some_and(|stream| stream.sequencer.is_finished());
+            if !(finished
+                || (eof && !self.waiting.is_empty())
+                || self.waiting_bytes() > MAX_WAITING_BYTES)
+            {
+                return None;
+            }
+            debug!(
+                "Incomplete message of {} bytes dropped ({})",
+                available,
+                self.active
+                    .1
+                    .map(|connection| connection.to_string())
+                    .unwrap_or_else(|| String::from("datagrams"))
+            );
+            if let Some(stream) = self.streams.get_mut(&self.active) {
+                stream.buffer.clear();
+            }
+            self.provided = 0;
+            self.dropped += available;
+        }
+        while let Some(key) = self.waiting.pop_front() {
+            self.active = key;
+            let available = self.current().len();
+            if available > 0 {
+                return Some(self.provide(available, skipped));
+            }
+        }
+        None
+    }
+
+    fn provide(&mut self, newly_loaded: usize, skipped: usize) -> ReloadInfo {
+        self.provided = self.current().len();
+        self.consumed = false;
+        let dropped = std::mem::take(&mut self.dropped);
+        ReloadInfo::new(
+            newly_loaded,
+            self.current().len(),
+            skipped + dropped,
+            self.streams
+                .get(&self.active)
+                .and_then(|stream| stream.last_known_ts),
+        )
+    }
+
+    fn waiting_bytes(&self) -> usize {
+        self.waiting
+            .iter()
+            .filter_map(|key| self.streams.get(key))
+            .map(|stream| stream.buffer.len())
+            .sum()
+    }
+
+    pub fn current(&self) -> &[u8] {
+        self.streams
+            .get(&self.active)
+            .map(|stream| stream.buffer.buf())
+            .unwrap_or_default()
+    }
+
+    pub fn consume(&mut self, offset: usize) {
+        if let Some(stream) = self.streams.get_mut(&self.active) {
+            stream.buffer.consume(offset);
+            self.provided = self.provided.saturating_sub(offset);
+            self.consumed = true;
+        }
+    }
+
+    /// interface, on which the bytes of the active stream were captured
+    pub fn interface(&self) -> Option<u32> {
+        self.active.0
+    }
+
+    /// TCP connections, which carried data so far, in order of their first data
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(port: u16) -> Connection {
+        Connection {
+            source: SocketAddr::from(([10, 0, 0, 1], port)),
+            destination: SocketAddr::from(([10, 0, 0, 2], 3490)),
+        }
+    }
+
+    fn segment(seq: u32) -> Segment {
+        Segment {
+            seq,
+            ..Default::default()
+        }
+    }
+
+    /// parses everything provided by the streams, like the producer does with a
+    /// parser accepting any bytes
+    fn parsed(streams: &mut Streams, eof: bool) -> Vec<(Vec<u8>, usize)> {
+        let mut parsed = vec![];
+        while let Some(info) = streams.reload(0, eof) {
+            let bytes = streams.current().to_vec();
+            streams.consume(bytes.len());
+            parsed.push((bytes, info.skipped_bytes));
+        }
+        parsed
+    }
+
+    #[test]
+    fn test_out_of_order_and_retransmitted_segments() {
+        let mut streams = Streams::default();
+        let tcp = connection(40000);
+        streams.add(
+            None,
+            Some((
+                tcp,
+                Segment {
+                    seq: u32::MAX - 1,
+                    syn: true,
+                    ..Default::default()
+                },
+            )),
+            &[],
+            None,
+        );
+        // sequence numbers wrap around
+        streams.add(None, Some((tcp, segment(5))), b"ghi", None);
+        streams.add(None, Some((tcp, segment(u32::MAX))), b"abc", None);
+        streams.add(None, Some((tcp, segment(u32::MAX))), b"abc", None);
+        streams.add(None, Some((tcp, segment(1))), b"cdef", None);
+        assert_eq!(
+            parsed(&mut streams, false),
+            vec![(b"abcdefghi".to_vec(), 0)]
+        );
+    }
+
+    #[test]
+    fn test_interleaved_connections() {
+        let mut streams = Streams::default();
+        let (first, second) = (connection(40000), connection(40001));
+        streams.add(None, Some((first, segment(100))), b"first ", None);
+        streams.add(None, Some((second, segment(500))), b"second ", None);
+        streams.add(None, None, b"datagram", None);
+        streams.add(None, Some((first, segment(106))), b"message", None);
+        assert_eq!(
+            parsed(&mut streams, false),
+            vec![
+                (b"first message".to_vec(), 0),
+                (b"second ".to_vec(), 0),
+                (b"datagram".to_vec(), 0)
+            ]
+        );
+        assert_eq!(streams.connections(), &[first, second]);
+    }
+
+    #[test]
+    fn test_incomplete_message() {
+        let mut streams = Streams::default();
+        let (first, second) = (connection(40000), connection(40001));
+        streams.add(None, Some((first, segment(0))), b"incomplete", None);
+        // parser needs more bytes
+        assert!(streams.reload(0, false).is_some());
+        assert!(streams.reload(0, false).is_none());
+        streams.add(None, Some((second, segment(0))), b"complete", None);
+        assert!(streams.reload(0, false).is_none());
+        let info = streams.reload(0, true).expect("other stream is provided");
+        assert_eq!(info.skipped_bytes, 10);
+        assert_eq!(streams.current(), b"complete");
+        // without other streams, the incomplete message is kept, the file might grow
+        streams.add(None, Some((second, segment(8))), b" and incomplete", None);
+        streams.consume(8);
+        assert!(streams.reload(0, true).is_some());
+        assert!(streams.reload(0, true).is_none());
+        assert_eq!(streams.current(), b" and incomplete");
+    }
+
+    #[test]
+    fn test_missing_segment_skipped() {
+        let mut streams = Streams::default();
+        let tcp = connection(40000);
+        streams.add(None, Some((tcp, segment(0))), b"a", None);
+        for seq in 0..=MAX_PENDING_SEGMENTS as u32 {
+            streams.add(None, Some((tcp, segment(2 + seq))), b"c", None);
+        }
+        let bytes = parsed(&mut streams, false)
+            .into_iter()
+            .flat_map(|(bytes, _)| bytes)
+            .collect::<Vec<u8>>();
+        assert_eq!(bytes.len(), 2 + MAX_PENDING_SEGMENTS);
+        assert_eq!(bytes[0], b'a');
+    }
+}
@@ -4,18 +4,18 @@ use crate::{
 };
 use async_trait::async_trait;
 use buf_redux::{policy::MinBuffered, BufReader as ReduxReader};
-use std::io::{BufRead, Read, Seek};
+use std::io::{BufRead, Read};
 
 pub struct BinaryByteSource<R>
 where
-    R: Read + Seek,
+    R: Read,
 {
     reader: ReduxReader<R, MinBuffered>,
 }
 
 impl<R> BinaryByteSource<R>
 where
-    R: Read + Seek + Unpin,
+    R: Read + Unpin,
 {
     /// create a new `BinaryByteSource` with default buffer settings for reading
     pub fn new(input: R) -> BinaryByteSource<R> {
@@ -35,7 +35,7 @@ where
 }
 
 #[async_trait]
-impl<R: Read + Send + Sync + Seek> ByteSource for BinaryByteSource<R> {
+impl<R: Read + Send + Sync> ByteSource for BinaryByteSource<R> {
     async fn reload(
         &mut self,
         _: Option<&SourceFilter>,
@@ -0,0 +1,177 @@
+use std::{
+    fs::{File, Metadata},
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// how the file at a path has changed compared to the file being read from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// the path points to another file now (e.g. `app.log` was moved to `app.log.1`
+    /// and a new `app.log` was created)
+    Rotated,
+    /// the file is smaller than the position it was read up to
+    Truncated,
+}
+
+/// identifies a file independent of its path, which is device and inode on unix.
+/// Not available on other platforms, where only truncation can be detected.
+pub fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// checks whether the file at `path` was rotated or truncated since `file` was
+/// opened and read up to `position`. A missing path is not taken as a rotation,
+/// because the new file might not be created yet.
+pub fn rotation(file: &File, path: &Path, position: u64) -> io::Result<Option<Rotation>> {
+    let current = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let opened = file.metadata()?;
+    Ok(if file_id(&current) != file_id(&opened) {
+        Some(Rotation::Rotated)
+    } else if current.len() < position {
+        Some(Rotation::Truncated)
+    } else {
+        None
+    })
+}
+
+/// Reads a file, which is written to while reading and might be rotated or
+/// truncated meanwhile (like logrotate does with `create` or `copytruncate`).
+/// Once the end of the file is reached:
+/// - if the path points to a new file, whatever was written to the rotated
+///   file in the meantime is read and the new file is continued with
+/// - if the file was truncated, it is read again from the start
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    position: u64,
+}
+
+impl RotatingFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path.as_ref())?;
+        Self::with_file(path, file)
+    }
+
+    /// continues reading a file, which was opened from `path` before, at its
+    /// current position
+    pub fn with_file<P: AsRef<Path>>(path: P, mut file: File) -> io::Result<Self> {
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            position: file.stream_position()?,
+            file,
+        })
+    }
+
+    pub fn rotation(&self) -> io::Result<Option<Rotation>> {
+        rotation(&self.file, &self.path, self.position)
+    }
+}
+
+impl Read for RotatingFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.file.read(buf)?;
+        if len > 0 || buf.is_empty() {
+            self.position += len as u64;
+            return Ok(len);
+        }
+        match self.rotation()? {
+            None => Ok(0),
+            Some(Rotation::Rotated) => {
+                // the tail might have been written right before the rotation
+                let len = self.file.read(buf)?;
+                if len > 0 {
+                    self.position += len as u64;
+                    return Ok(len);
+                }
+                debug!(
+                    "{} was rotated, continue with new file",
+                    self.path.display()
+                );
+                self.file = File::open(&self.path)?;
+                self.position = 0;
+                self.read(buf)
+            }
+            Some(Rotation::Truncated) => {
+                debug!("{} was truncated, read from start", self.path.display());
+                self.file.seek(SeekFrom::Start(0))?;
+                self.position = 0;
+                self.read(buf)
+            }
+        }
+    }
+}
+
+impl Seek for RotatingFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.file.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::OpenOptions, io::Write};
+
+    fn read_available(file: &mut RotatingFile) -> String {
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rotated_file() {
+        let path = std::env::temp_dir().join(format!("{}.log", uuid::Uuid::new_v4()));
+        let rotated = path.with_extension("log.1");
+        let mut writer = File::create(&path).unwrap();
+        writer.write_all(b"first\n").unwrap();
+        let mut file = RotatingFile::open(&path).unwrap();
+        assert_eq!(read_available(&mut file), "first\n");
+        assert_eq!(file.rotation().unwrap(), None);
+        std::fs::rename(&path, &rotated).unwrap();
+        // new file isn't created yet
+        assert_eq!(file.rotation().unwrap(), None);
+        writer.write_all(b"tail\n").unwrap();
+        let mut new_writer = File::create(&path).unwrap();
+        new_writer.write_all(b"second\n").unwrap();
+        assert_eq!(file.rotation().unwrap(), Some(Rotation::Rotated));
+        assert_eq!(read_available(&mut file), "tail\nsecond\n");
+        assert_eq!(file.rotation().unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let path = std::env::temp_dir().join(format!("{}.log", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"first\nsecond\n").unwrap();
+        let mut file = RotatingFile::open(&path).unwrap();
+        assert_eq!(read_available(&mut file), "first\nsecond\n");
+        std::fs::write(&path, b"third\n").unwrap();
+        assert_eq!(file.rotation().unwrap(), Some(Rotation::Truncated));
+        assert_eq!(read_available(&mut file), "third\n");
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"fourth\n")
+            .unwrap();
+        assert_eq!(read_available(&mut file), "fourth\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -7,7 +7,7 @@ use shellexpand::tilde;
 use std::{collections::HashMap, ffi::OsString, path::PathBuf, process::Stdio};
 use thiserror::Error;
 use tokio::{
-    io::AsyncWriteExt,
+    io::{AsyncReadExt, AsyncWriteExt},
     process::{Child, ChildStderr, ChildStdin, ChildStdout, Command},
     select,
 };
@@ -33,14 +33,32 @@ pub enum ProcessError {
     Unrecoverable(String),
 }
 
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+enum Stdout {
+    /// stdout and stderr are read line by line and provided together
+    Lines(
+        FramedRead<ChildStdout, LinesCodec>,
+        FramedRead<ChildStderr, LinesCodec>,
+    ),
+    /// stdout bytes are forwarded unchanged, stderr is provided by [`ProcessStderrSource`]
+    Raw(ChildStdout, Vec<u8>),
+}
+
 pub struct ProcessSource {
     process: Child,
     buffer: Buffer,
-    stdout: FramedRead<ChildStdout, LinesCodec>,
-    stderr: FramedRead<ChildStderr, LinesCodec>,
+    stdout: Stdout,
     stdin: ChildStdin,
 }
 
+/// Provides the stderr output of a process started with [`ProcessSource::new_raw`]
+/// line by line.
+pub struct ProcessStderrSource {
+    buffer: Buffer,
+    stderr: FramedRead<ChildStderr, LinesCodec>,
+}
+
 impl Drop for ProcessSource {
     fn drop(&mut self) {
         if let Err(err) = futures::executor::block_on(self.process.kill()) {
@@ -115,11 +133,11 @@ impl ProcessSource {
             .map_err(|e| ProcessError::Setup(format!("{e}")))
     }
 
-    pub async fn new(
+    fn start(
         command: String,
         cwd: PathBuf,
         envs: HashMap<String, String>,
-    ) -> Result<Self, ProcessError> {
+    ) -> Result<(Child, ChildStdout, ChildStderr, ChildStdin), ProcessError> {
         let mut args = ProcessSource::parse_command(&command)?;
         let cmd = if args.is_empty() {
             return Err(ProcessError::Setup(format!(
@@ -129,32 +147,60 @@ impl ProcessSource {
             args.remove(0)
         };
         let mut process = ProcessSource::spawn(cmd, args, cwd, envs)?;
-        let stdout = codec::FramedRead::new(
-            process
-                .stdout
-                .take()
-                .ok_or_else(|| ProcessError::Setup(String::from("Fail to get stdout handle")))?,
-            LinesCodec::default(),
-        );
-        let stderr = codec::FramedRead::new(
-            process
-                .stderr
-                .take()
-                .ok_or_else(|| ProcessError::Setup(String::from("Fail to get stderr handle")))?,
-            LinesCodec::default(),
-        );
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or_else(|| ProcessError::Setup(String::from("Fail to get stdout handle")))?;
+        let stderr = process
+            .stderr
+            .take()
+            .ok_or_else(|| ProcessError::Setup(String::from("Fail to get stderr handle")))?;
         let stdin = process
             .stdin
             .take()
             .ok_or_else(|| ProcessError::Setup(String::from("Fail to get stdin handle")))?;
+        Ok((process, stdout, stderr, stdin))
+    }
+
+    pub async fn new(
+        command: String,
+        cwd: PathBuf,
+        envs: HashMap<String, String>,
+    ) -> Result<Self, ProcessError> {
+        let (process, stdout, stderr, stdin) = ProcessSource::start(command, cwd, envs)?;
         Ok(Self {
             process,
             buffer: Buffer::new(),
-            stdout,
-            stderr,
+            stdout: Stdout::Lines(
+                codec::FramedRead::new(stdout, LinesCodec::default()),
+                codec::FramedRead::new(stderr, LinesCodec::default()),
+            ),
             stdin,
         })
     }
+
+    /// starts the process in raw mode: stdout is forwarded byte by byte without
+    /// any framing, so binary output can be parsed. The stderr output is returned
+    /// as a separate source.
+    pub async fn new_raw(
+        command: String,
+        cwd: PathBuf,
+        envs: HashMap<String, String>,
+    ) -> Result<(Self, ProcessStderrSource), ProcessError> {
+        let (process, stdout, stderr, stdin) = ProcessSource::start(command, cwd, envs)?;
+        Ok((
+            Self {
+                process,
+                buffer: Buffer::new(),
+                stdout: Stdout::Raw(stdout, vec![0u8; READ_CHUNK_SIZE]),
+                stdin,
+            },
+            ProcessStderrSource {
+                buffer: Buffer::new(),
+                stderr: codec::FramedRead::new(stderr, LinesCodec::default()),
+            },
+        ))
+    }
 }
 
 #[async_trait]
@@ -163,13 +209,25 @@ impl ByteSource for ProcessSource {
         &mut self,
         _filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
+        let (stdout, stderr) = match &mut self.stdout {
+            Stdout::Lines(stdout, stderr) => (stdout, stderr),
+            Stdout::Raw(stdout, tmp_buffer) => {
+                let len = stdout.read(tmp_buffer).await.map_err(SourceError::Io)?;
+                if len == 0 {
+                    return Ok(None);
+                }
+                self.buffer.reserve(len);
+                self.buffer.copy_from_slice(&tmp_buffer[..len]);
+                return Ok(Some(ReloadInfo::new(len, self.buffer.len(), 0, None)));
+            }
+        };
         let mut closing = false;
         let mut output;
         loop {
             if !closing {
                 output = select! {
-                    res = self.stdout.next() => res,
-                    res = self.stderr.next() => {
+                    res = stdout.next() => res,
+                    res = stderr.next() => {
                         if res.is_none() {
                             closing = true;
                         }
@@ -180,20 +238,11 @@ impl ByteSource for ProcessSource {
                     break;
                 }
             } else {
-                output = self.stdout.next().await;
+                output = stdout.next().await;
                 break;
             }
         }
-        if let Some(Ok(line)) = output {
-            let stored = line.len() + 1;
-            self.buffer.copy_from_slice(line.as_bytes());
-            self.buffer.copy_from_slice(b"\n");
-            Ok(Some(ReloadInfo::new(stored, stored, 0, None)))
-        } else if let Some(Err(err)) = output {
-            Err(SourceError::Unrecoverable(format!("{err}")))
-        } else {
-            Ok(None)
-        }
+        store_line(&mut self.buffer, output)
     }
 
     fn current_slice(&self) -> &[u8] {
@@ -222,6 +271,45 @@ impl ByteSource for ProcessSource {
     }
 }
 
+fn store_line(
+    buffer: &mut Buffer,
+    output: Option<Result<String, codec::LinesCodecError>>,
+) -> Result<Option<ReloadInfo>, SourceError> {
+    match output {
+        Some(Ok(line)) => {
+            let stored = line.len() + 1;
+            buffer.copy_from_slice(line.as_bytes());
+            buffer.copy_from_slice(b"\n");
+            Ok(Some(ReloadInfo::new(stored, stored, 0, None)))
+        }
+        Some(Err(err)) => Err(SourceError::Unrecoverable(format!("{err}"))),
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl ByteSource for ProcessStderrSource {
+    async fn reload(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        let output = self.stderr.next().await;
+        store_line(&mut self.buffer, output)
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset);
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
 #[tokio::test]
 async fn test_process() -> Result<(), ProcessError> {
     use std::env;
@@ -267,3 +355,35 @@ async fn test_parsing() -> Result<(), ProcessError> {
     assert_eq!(parsed[3], OsString::from("arg3"));
     Ok(())
 }
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_raw_process() -> Result<(), ProcessError> {
+    let (mut stdout, mut stderr) = ProcessSource::new_raw(
+        String::from(r#"sh -c "printf 'a\000\377\nb'; echo failed >&2""#),
+        std::env::current_dir().unwrap(),
+        HashMap::new(),
+    )
+    .await?;
+    let mut received = vec![];
+    while stdout
+        .reload(None)
+        .await
+        .expect("Reload data from process source failed")
+        .is_some()
+    {
+        received.extend_from_slice(stdout.current_slice());
+        stdout.consume(stdout.len());
+    }
+    assert_eq!(received, b"a\x00\xff\nb");
+    stderr.reload(None).await.expect("Reload stderr failed");
+    assert_eq!(stderr.current_slice(), b"failed\n");
+    // same as in test_process, the process has to be killed before it is dropped
+    let _ = stdout.process.kill().await;
+    assert!(stderr
+        .reload(None)
+        .await
+        .expect("Reload stderr failed")
+        .is_none());
+    Ok(())
+}
@@ -1,5 +1,6 @@
+use crate::SourceFilter;
 use indexer_base::config::MulticastInfo;
-use parsers::dlt;
+use parsers::{dlt, someip, text::TextEncoding};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 use uuid::Uuid;
@@ -9,7 +10,10 @@ use uuid::Uuid;
 pub enum ParserType {
     Dlt(DltParserSettings),
     SomeIp(SomeIpParserSettings),
-    Text,
+    Text(Option<TextParserSettings>),
+    JsonLines(JsonLinesParserSettings),
+    Regex(RegexParserSettings),
+    Syslog,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,7 +21,13 @@ pub struct DltParserSettings {
     pub filter_config: Option<dlt::DltFilterConfig>,
     pub fibex_file_paths: Option<Vec<String>>,
     pub with_storage_header: bool,
+    /// messages start with the serial header (`DLS` + 0x01) as sent over serial lines
+    #[serde(default)]
+    pub with_serial_header: bool,
     pub tz: Option<String>,
+    /// FIBEX files of SOME/IP services, to decode SOME/IP messages of network traces
+    #[serde(default)]
+    pub someip_fibex_file_paths: Option<Vec<String>>,
     #[serde(skip)]
     pub fibex_metadata: Option<dlt::FibexMetadata>,
 }
@@ -28,7 +38,9 @@ impl Default for DltParserSettings {
             filter_config: None,
             fibex_file_paths: None,
             with_storage_header: true,
+            with_serial_header: false,
             tz: None,
+            someip_fibex_file_paths: None,
             fibex_metadata: None,
         }
     }
@@ -43,7 +55,9 @@ impl DltParserSettings {
             filter_config,
             fibex_file_paths,
             with_storage_header: true,
+            with_serial_header: false,
             tz: None,
+            someip_fibex_file_paths: None,
             fibex_metadata: None,
         }
     }
@@ -65,6 +79,35 @@ impl DltParserSettings {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SomeIpParserSettings {
     pub fibex_file_paths: Option<Vec<String>>,
+    /// messages not matching the configuration are dropped while parsing
+    #[serde(default)]
+    pub filter_config: Option<someip::filtering::SomeipFilterConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TextParserSettings {
+    pub encoding: TextEncoding,
+    /// regular expression matching the first line of a record; following lines,
+    /// which don't match, are grouped into the same record
+    pub record_start: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct JsonLinesParserSettings {
+    /// paths of the values shown as columns, e.g. `ctx.user.id`
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegexParserSettings {
+    /// regular expression with named captures, each capture is shown as a column
+    pub pattern: String,
+    /// name of the capture holding the timestamp
+    pub timestamp: Option<String>,
+    /// chrono format of the timestamp, RFC 3339 or milliseconds since epoch if not set
+    pub timestamp_format: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -73,6 +116,9 @@ pub enum Transport {
     TCP(TCPTransportConfig),
     UDP(UDPTransportConfig),
     Serial(SerialTransportConfig),
+    Syslog(SyslogTransportConfig),
+    UnixSocket(UnixSocketTransportConfig),
+    Fifo(FifoTransportConfig),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,6 +126,10 @@ pub struct ProcessTransportConfig {
     pub cwd: PathBuf,
     pub command: String,
     pub envs: HashMap<String, String>,
+    /// forward stdout bytes unchanged instead of reading it line by line,
+    /// stderr is provided as a separate source
+    #[serde(default)]
+    pub raw: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,11 +142,48 @@ pub struct SerialTransportConfig {
     pub stop_bits: u8,
     pub send_data_delay: u8,
     pub exclusive: bool,
+    /// forward received bytes unchanged instead of reading line by line
+    #[serde(default)]
+    pub raw: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TCPTransportConfig {
     pub bind_addr: String,
+    /// accept incoming connections on `bind_addr` instead of connecting to it
+    #[serde(default)]
+    pub listen: Option<TcpListenConfig>,
+    /// connect again if the connection is lost
+    #[serde(default)]
+    pub reconnect: Option<TcpReconnectConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TcpReconnectConfig {
+    /// time to wait before the first attempt
+    pub interval_ms: u64,
+    /// factor by which the time between attempts grows with each failed attempt
+    pub backoff: f32,
+    /// give up after this many failed attempts, retry forever if not set
+    pub max_attempts: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TcpListenConfig {
+    pub policy: TcpClientPolicy,
+}
+
+/// how to deal with a client connecting while another one is connected already
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub enum TcpClientPolicy {
+    /// close the new connection
+    #[default]
+    Reject,
+    /// close the current connection and continue with the new one
+    Replace,
+    /// read from all connections at the same time
+    Multiplex,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -105,6 +192,35 @@ pub struct UDPTransportConfig {
     pub multicast: Vec<MulticastInfo>,
 }
 
+/// listens for syslog messages on UDP and TCP
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyslogTransportConfig {
+    pub bind_addr: String,
+}
+
+/// reads from a unix domain socket (unix only)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnixSocketTransportConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub kind: UnixSocketKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub enum UnixSocketKind {
+    /// connect to a listening socket at `path`
+    #[default]
+    Stream,
+    /// bind a socket at `path` and receive the datagrams sent to it
+    Datagram,
+}
+
+/// reads from a named pipe (unix only)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FifoTransportConfig {
+    pub path: PathBuf,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum FileFormat {
     PcapNG,
@@ -117,13 +233,42 @@ pub enum FileFormat {
 pub enum ObserveOrigin {
     File(String, FileFormat, PathBuf),
     Concat(Vec<(String, FileFormat, PathBuf)>),
+    /// files inside of a zip or tar archive (given by their path in the archive),
+    /// observed one after another like concatenated files
+    Archive(PathBuf, Vec<(String, FileFormat, String)>),
+    /// all files of a directory with names matching `glob` (e.g. `*.dlt`), observed
+    /// one after another like concatenated files. Files appearing later are observed
+    /// as well; the latest file is followed until a newer one appears.
+    Directory {
+        path: PathBuf,
+        glob: String,
+        format: FileFormat,
+        #[serde(default)]
+        order: FileOrder,
+    },
     Stream(String, Transport),
 }
 
+/// order in which the files of a directory are observed
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub enum FileOrder {
+    #[default]
+    Name,
+    /// by time of the last modification, oldest first
+    Modified,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ObserveOptions {
     pub origin: ObserveOrigin,
     pub parser: ParserType,
+    /// selects the packets of network traces (pcap, pcapng) and UDP streams
+    #[serde(default)]
+    pub filter: Option<SourceFilter>,
+    /// each TCP connection of a network trace file (pcap, pcapng) is observed as
+    /// an own source, one after another
+    #[serde(default)]
+    pub split_connections: bool,
 }
 
 impl ObserveOptions {
@@ -131,6 +276,8 @@ impl ObserveOptions {
         ObserveOptions {
             origin: ObserveOrigin::File(Uuid::new_v4().to_string(), file_origin, filename),
             parser,
+            filter: None,
+            split_connections: false,
         }
     }
 }
@@ -1,5 +1,7 @@
 #![deny(unused_crate_dependencies)]
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use thiserror::Error;
 
 #[macro_use]
@@ -16,7 +18,7 @@ pub mod sde;
 pub mod serial;
 pub mod socket;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransportProtocol {
     TCP,
     UDP,
@@ -33,9 +35,38 @@ impl From<etherparse::TransportSlice<'_>> for TransportProtocol {
     }
 }
 
-#[derive(Debug)]
+/// Selects the packets of network traces (pcap, pcapng), the datagrams of UDP
+/// sources and the data of TCP connections, which are passed on to the parser. Criteria which aren't set match
+/// any packet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SourceFilter {
-    transport: Option<TransportProtocol>,
+    pub transport: Option<TransportProtocol>,
+    pub source_ip: Option<IpAddr>,
+    pub destination_ip: Option<IpAddr>,
+    pub source_port: Option<u16>,
+    pub destination_port: Option<u16>,
+    /// matches the source or the destination port
+    pub port: Option<u16>,
+    /// VLAN id (802.1Q), either of both tags of double tagged packets matches
+    pub vlan_id: Option<u16>,
+    /// id of the capturing interface, only used for pcapng files
+    pub interface_id: Option<u32>,
+}
+
+impl SourceFilter {
+    fn wanted<T: PartialEq>(wanted: &Option<T>, actual: Option<T>) -> bool {
+        match wanted {
+            Some(wanted) => actual.as_ref() == Some(wanted),
+            None => true,
+        }
+    }
+
+    /// checks the sender of a datagram, criteria of the receiving side are ignored
+    pub fn matches_sender(&self, sender: &std::net::SocketAddr) -> bool {
+        Self::wanted(&self.source_ip, Some(sender.ip()))
+            && Self::wanted(&self.source_port, Some(sender.port()))
+    }
 }
 
 #[derive(Debug)]
@@ -100,6 +131,12 @@ pub trait ByteSource: Send + Sync {
         self.len() == 0
     }
 
+    /// Name of the origin of the currently loaded bytes, in case the source combines
+    /// several of them (e.g. the capturing interfaces of a network trace)
+    fn origin(&self) -> Option<&str> {
+        None
+    }
+
     /// will load more bytes from the underlying source
     /// when the source has reached it's end, this function
     /// will return Ok((None, _))
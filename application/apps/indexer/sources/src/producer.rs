@@ -2,19 +2,25 @@ use crate::{sde::SdeMsg, ByteSource, ReloadInfo, SourceFilter};
 use async_stream::stream;
 use log::warn;
 use parsers::{Error as ParserError, LogMessage, MessageStreamItem, Parser};
-use std::marker::PhantomData;
+use std::{collections::VecDeque, marker::PhantomData, ops::Range, time::Duration};
 use tokio::{
     select,
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time::sleep,
 };
 use tokio_stream::Stream;
 
 pub type SdeSender = UnboundedSender<SdeMsg>;
 pub type SdeReceiver = UnboundedReceiver<SdeMsg>;
 
+/// messages held back by the parser are flushed once the source didn't provide
+/// more data for this long
+const FLUSH_DELAY: Duration = Duration::from_millis(300);
+
 enum Next {
     Read((usize, usize, usize)),
     Sde(Option<SdeMsg>),
+    Flush,
 }
 
 #[derive(Debug)]
@@ -32,7 +38,11 @@ where
     _phantom_data: Option<PhantomData<T>>,
     total_loaded: usize,
     total_skipped: usize,
+    total_consumed: usize,
+    lost: Option<Range<usize>>,
+    pending: VecDeque<(usize, MessageStreamItem<T>)>,
     done: bool,
+    follow: bool,
     rx_sde: Option<SdeReceiver>,
 }
 
@@ -48,10 +58,29 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
             _phantom_data: None,
             total_loaded: 0,
             total_skipped: 0,
+            total_consumed: 0,
+            lost: None,
+            pending: VecDeque::new(),
             done: false,
+            follow: false,
             rx_sde,
         }
     }
+    /// passes the filter to the source on each reload, e.g. to select the packets
+    /// of network traces
+    pub fn with_filter(mut self, filter: Option<SourceFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// the source is followed while it grows (e.g. a tailed file): once its end is
+    /// reached `Done` is delivered, but the stream continues with the bytes the
+    /// source provides on the next reload
+    pub fn with_follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
     /// create a stream of pairs that contain the count of all consumed bytes and the
     /// MessageStreamItem
     pub fn as_stream(&mut self) -> impl Stream<Item = (usize, MessageStreamItem<T>)> + '_ {
@@ -62,24 +91,56 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
         }
     }
 
+    /// like `as_stream`, but each item comes with the origin of its bytes, if the
+    /// source provides it (see `ByteSource::origin`)
+    pub fn as_stream_with_origin(
+        &mut self,
+    ) -> impl Stream<Item = (usize, MessageStreamItem<T>, Option<String>)> + '_ {
+        stream! {
+            while let Some((consumed, item)) = self.read_next_segment().await {
+                let origin = self.byte_source.origin().map(String::from);
+                yield (consumed, item, origin);
+            }
+        }
+    }
+
     async fn read_next_segment(&mut self) -> Option<(usize, MessageStreamItem<T>)> {
+        if let Some(pending) = self.pending.pop_front() {
+            return Some(pending);
+        }
         if self.done {
             debug!("done...no next segment");
             return None;
         }
         self.index += 1;
         let (_newly_loaded, mut available, mut skipped_bytes) = 'outer: loop {
+            // bytes already loaded are parsed before waiting for the source
+            let buffered = self.byte_source.len();
+            if buffered > 0 {
+                break 'outer (0, buffered, 0);
+            }
+            // reloading isn't cancel safe, so it's only given up to flush a message
+            // the parser actually holds back
+            let flush = self.parser.has_pending();
             if let Some(mut rx_sde) = self.rx_sde.take() {
                 'inner: loop {
                     // SDE mode: listening next chunk and possible incoming message for source
                     match select! {
                         msg = rx_sde.recv() => Next::Sde(msg),
                         read = self.do_reload() => Next::Read(read.unwrap_or((0, 0, 0))),
+                        _ = sleep(FLUSH_DELAY), if flush => Next::Flush,
                     } {
                         Next::Read(next) => {
                             self.rx_sde = Some(rx_sde);
                             break 'outer next;
                         }
+                        Next::Flush => {
+                            self.rx_sde = Some(rx_sde);
+                            if let Some(flushed) = self.flush() {
+                                return Some(flushed);
+                            }
+                            continue 'outer;
+                        }
                         Next::Sde(msg) => {
                             if let Some((msg, tx_response)) = msg {
                                 if tx_response
@@ -105,7 +166,17 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
                 }
             } else {
                 // NoSDE mode: listening only next chunk
-                break 'outer self.do_reload().await.unwrap_or((0, 0, 0));
+                match select! {
+                    read = self.do_reload() => Next::Read(read.unwrap_or((0, 0, 0))),
+                    _ = sleep(FLUSH_DELAY), if flush => Next::Flush,
+                } {
+                    Next::Read(next) => break 'outer next,
+                    _ => {
+                        if let Some(flushed) = self.flush() {
+                            return Some(flushed);
+                        }
+                    }
+                }
             };
         };
         // 1. buffer loaded? if not, fill buffer with frame data
@@ -121,8 +192,22 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
             );
             if available == 0 {
                 trace!("No more bytes available from source");
-                self.done = true;
-                return Some((0, MessageStreamItem::Done));
+                if !self.follow {
+                    self.done = true;
+                    // a parser might still hold back messages until it knows
+                    // there will be no more data
+                    while let Some(m) = self.parser.finish() {
+                        self.pending.push_back((0, MessageStreamItem::Item(m)));
+                    }
+                } else {
+                    // the end is reached for now, nothing should be held back meanwhile
+                    while let Some(m) = self.parser.flush() {
+                        self.pending.push_back((0, MessageStreamItem::Item(m)));
+                    }
+                }
+                self.pending.push_back((0, MessageStreamItem::Done));
+                let (used_bytes, item) = self.pending.pop_front()?;
+                return Some(self.with_lost(used_bytes, item));
             }
             match self
                 .parser
@@ -135,20 +220,22 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
                         "Extracted a valid message, consumed {} bytes (total used {} bytes)",
                         consumed, total_used_bytes
                     );
-                    self.byte_source.consume(consumed);
-                    return Some((total_used_bytes, MessageStreamItem::Item(m)));
+                    self.consume(consumed);
+                    return Some(self.with_lost(total_used_bytes, MessageStreamItem::Item(m)));
                 }
                 Ok((rest, None)) => {
                     let consumed = available - rest.len();
-                    self.byte_source.consume(consumed);
+                    self.consume(consumed);
                     trace!("None, consumed {} bytes", consumed);
                     let total_used_bytes = consumed + skipped_bytes;
-                    return Some((total_used_bytes, MessageStreamItem::Skipped));
+                    return Some(self.with_lost(total_used_bytes, MessageStreamItem::Skipped));
                 }
                 Err(ParserError::Incomplete) => {
                     trace!("not enough bytes to parse a message");
-                    let (reloaded, _available_bytes, skipped) = self.do_reload().await?;
-                    available += reloaded;
+                    let (_reloaded, _available_bytes, skipped) = self.do_reload().await?;
+                    // sources might replace bytes, which cannot be completed anymore
+                    // (e.g. network traces with several connections)
+                    available = self.byte_source.len();
                     skipped_bytes += skipped;
                     continue;
                 }
@@ -160,27 +247,73 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
                     return None;
                 }
                 Err(ParserError::Parse(s)) => {
+                    // drop only the bytes that cannot be the start of the next message
+                    // and try again from there
+                    let dropped = self
+                        .parser
+                        .resync(self.byte_source.current_slice())
+                        .clamp(1, available);
                     trace!(
-                    "No parse possible, try next batch of data ({}), skipped {} more bytes ({} already)",
-                    s, available, skipped_bytes
-                );
-                    // skip all currently available bytes
-                    self.byte_source.consume(available);
-                    skipped_bytes += available;
+                        "No parse possible ({}), drop {} of {} bytes to resync",
+                        s,
+                        dropped,
+                        available
+                    );
+                    self.consume(dropped);
+                    self.mark_lost(dropped);
                     available = self.byte_source.len();
-                    if let Some((reloaded, _available_bytes, skipped)) = self.do_reload().await {
-                        available += reloaded;
-                        skipped_bytes += skipped;
-                    } else {
-                        let unused = skipped_bytes + available;
-                        self.done = true;
-                        return Some((unused, MessageStreamItem::Done));
+                    if available == 0 {
+                        if let Some((reloaded, _available_bytes, skipped)) = self.do_reload().await
+                        {
+                            available += reloaded;
+                            skipped_bytes += skipped;
+                        }
                     }
                 }
             }
         }
     }
 
+    /// the source didn't provide more data for a while, messages held back by the
+    /// parser are delivered now
+    fn flush(&mut self) -> Option<(usize, MessageStreamItem<T>)> {
+        while let Some(m) = self.parser.flush() {
+            self.pending.push_back((0, MessageStreamItem::Item(m)));
+        }
+        self.pending.pop_front()
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.byte_source.consume(bytes);
+        self.total_consumed += bytes;
+    }
+
+    /// remember that the last `bytes` consumed bytes could not be parsed
+    fn mark_lost(&mut self, bytes: usize) {
+        let end = self.total_consumed;
+        self.lost = Some(match self.lost.take() {
+            Some(lost) => lost.start..end,
+            None => (end - bytes)..end,
+        });
+    }
+
+    /// in case bytes were lost before `item`, the lost range is delivered first
+    /// and `item` is kept for the next call
+    fn with_lost(
+        &mut self,
+        used_bytes: usize,
+        item: MessageStreamItem<T>,
+    ) -> (usize, MessageStreamItem<T>) {
+        match self.lost.take() {
+            Some(lost) => {
+                warn!("Lost {} bytes ({:?}) while resyncing", lost.len(), lost);
+                self.pending.push_front((used_bytes, item));
+                (lost.len(), MessageStreamItem::Lost(lost))
+            }
+            None => (used_bytes, item),
+        }
+    }
+
     async fn do_reload(&mut self) -> Option<(usize, usize, usize)> {
         match self.byte_source.reload(self.filter.as_ref()).await {
             Ok(Some(ReloadInfo {
@@ -211,3 +344,224 @@ impl<T: LogMessage, P: Parser<T>, D: ByteSource> MessageProducer<T, P, D> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::raw::BinaryByteSource;
+    use parsers::ParseYield;
+    use serde::Serialize;
+    use std::{
+        fmt,
+        io::{Cursor, Write},
+    };
+    use tokio_stream::StreamExt;
+
+    /// a message is the magic byte `M` followed by one payload byte
+    #[derive(Debug, Serialize)]
+    struct TestMessage(u8);
+
+    impl fmt::Display for TestMessage {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl LogMessage for TestMessage {
+        fn to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+            writer.write_all(&[b'M', self.0])?;
+            Ok(2)
+        }
+    }
+
+    struct TestParser;
+
+    impl Parser<TestMessage> for TestParser {
+        fn parse<'a>(
+            &mut self,
+            input: &'a [u8],
+            _timestamp: Option<u64>,
+        ) -> Result<(&'a [u8], Option<ParseYield<TestMessage>>), ParserError> {
+            match input {
+                [b'M', payload, rest @ ..] => Ok((rest, Some(TestMessage(*payload).into()))),
+                [b'M'] => Err(ParserError::Incomplete),
+                _ => Err(ParserError::Parse("no magic".to_owned())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resync_after_parse_error() {
+        let input: Vec<u8> = b"M1xxxM2M3yM4".to_vec();
+        let source = BinaryByteSource::new(Cursor::new(input));
+        let mut producer = MessageProducer::new(TestParser, source, None);
+        let stream = producer.as_stream();
+        futures::pin_mut!(stream);
+        let mut messages = vec![];
+        let mut lost = vec![];
+        while let Some((_, item)) = stream.next().await {
+            match item {
+                MessageStreamItem::Item(ParseYield::Message(msg)) => messages.push(msg.0),
+                MessageStreamItem::Lost(range) => lost.push(range),
+                MessageStreamItem::Done => break,
+                _ => {}
+            }
+        }
+        assert_eq!(messages, vec![b'1', b'2', b'3', b'4']);
+        assert_eq!(lost, vec![2..5, 9..10]);
+    }
+
+    #[tokio::test]
+    async fn test_follow_growing_source() {
+        let path = std::env::temp_dir().join(format!("{}.bin", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"M1").unwrap();
+        let source = BinaryByteSource::new(std::fs::File::open(&path).unwrap());
+        let mut producer = MessageProducer::new(TestParser, source, None).with_follow(true);
+        let stream = producer.as_stream();
+        futures::pin_mut!(stream);
+        assert!(matches!(
+            stream.next().await.map(|(_, item)| item),
+            Some(MessageStreamItem::Item(ParseYield::Message(TestMessage(
+                b'1'
+            ))))
+        ));
+        assert!(matches!(
+            stream.next().await.map(|(_, item)| item),
+            Some(MessageStreamItem::Done)
+        ));
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"M2")
+            .unwrap();
+        assert!(matches!(
+            stream.next().await.map(|(_, item)| item),
+            Some(MessageStreamItem::Item(ParseYield::Message(TestMessage(
+                b'2'
+            ))))
+        ));
+        assert!(matches!(
+            stream.next().await.map(|(_, item)| item),
+            Some(MessageStreamItem::Done)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_finish_delivers_held_back_record() {
+        let input: Vec<u8> = b"A first\n  more\nA second\n  more".to_vec();
+        let source = BinaryByteSource::new(Cursor::new(input));
+        let parser = parsers::text::StringTokenizer::default()
+            .with_record_start("^A")
+            .unwrap();
+        let mut producer = MessageProducer::new(parser, source, None);
+        let stream = producer.as_stream();
+        futures::pin_mut!(stream);
+        let mut records = vec![];
+        while let Some((_, item)) = stream.next().await {
+            match item {
+                MessageStreamItem::Item(ParseYield::Message(msg)) => records.push(msg.to_string()),
+                MessageStreamItem::Done => break,
+                _ => {}
+            }
+        }
+        assert_eq!(
+            records,
+            vec!["A first\u{0006}  more", "A second\u{0006}  more"]
+        );
+    }
+
+    /// provides the chunks sent through a channel, waits while there are none
+    struct ChannelSource {
+        rx: UnboundedReceiver<Vec<u8>>,
+        buffer: Vec<u8>,
+        /// count of started reloads
+        reloads: usize,
+    }
+
+    impl ChannelSource {
+        fn new(rx: UnboundedReceiver<Vec<u8>>) -> Self {
+            Self {
+                rx,
+                buffer: vec![],
+                reloads: 0,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ByteSource for ChannelSource {
+        fn consume(&mut self, offset: usize) {
+            self.buffer.drain(..offset);
+        }
+
+        fn current_slice(&self) -> &[u8] {
+            &self.buffer
+        }
+
+        fn len(&self) -> usize {
+            self.buffer.len()
+        }
+
+        async fn reload(
+            &mut self,
+            _filter: Option<&SourceFilter>,
+        ) -> Result<Option<ReloadInfo>, crate::Error> {
+            self.reloads += 1;
+            Ok(self.rx.recv().await.map(|chunk| {
+                self.buffer.extend_from_slice(&chunk);
+                ReloadInfo::new(chunk.len(), self.buffer.len(), 0, None)
+            }))
+        }
+    }
+
+    async fn next_message<T: LogMessage, S: Stream<Item = (usize, MessageStreamItem<T>)>>(
+        stream: &mut std::pin::Pin<&mut S>,
+    ) -> String {
+        loop {
+            match stream.next().await {
+                Some((_, MessageStreamItem::Item(ParseYield::Message(msg)))) => {
+                    return msg.to_string()
+                }
+                Some(_) => continue,
+                None => panic!("no message"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_held_back_record_while_waiting() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let source = ChannelSource::new(rx);
+        let parser = parsers::text::StringTokenizer::default()
+            .with_record_start("^A")
+            .unwrap();
+        let mut producer = MessageProducer::new(parser, source, None);
+        let stream = producer.as_stream();
+        futures::pin_mut!(stream);
+        tx.send(b"A first\n  more\n".to_vec()).unwrap();
+        assert_eq!(next_message(&mut stream).await, "A first\u{0006}  more");
+        tx.send(b"A second\n".to_vec()).unwrap();
+        assert_eq!(next_message(&mut stream).await, "A second");
+    }
+
+    #[tokio::test]
+    async fn test_wait_without_held_back_message() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut producer = MessageProducer::new(TestParser, ChannelSource::new(rx), None);
+        tokio::spawn(async move {
+            tokio::time::sleep(FLUSH_DELAY * 3).await;
+            tx.send(b"M1M2".to_vec()).unwrap();
+        });
+        {
+            let stream = producer.as_stream();
+            futures::pin_mut!(stream);
+            assert_eq!(next_message(&mut stream).await, "49");
+            assert_eq!(next_message(&mut stream).await, "50");
+        }
+        // the waiting reload was never given up and the loaded bytes were parsed
+        // without another one
+        assert_eq!(producer.byte_source.reloads, 1);
+    }
+}
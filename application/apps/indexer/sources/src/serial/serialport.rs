@@ -13,27 +13,41 @@ use tokio::time::{sleep, Duration};
 use tokio_serial::{DataBits, FlowControl, Parity, SerialPortBuilderExt, SerialStream, StopBits};
 use tokio_util::codec::{Decoder, Encoder, Framed};
 
-struct LineCodec;
+/// Splits the received data into lines of valid UTF-8. In raw mode all received
+/// bytes are passed on unchanged, e.g. for DLT over serial.
+struct SerialCodec {
+    raw: bool,
+}
 
-impl Decoder for LineCodec {
-    type Item = String;
+impl Decoder for SerialCodec {
+    type Item = Vec<u8>;
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if self.raw {
+            return Ok(if src.is_empty() {
+                None
+            } else {
+                Some(src.split().to_vec())
+            });
+        }
         match &src.iter().position(|b| *b == b'\n') {
-            Some(n) => match str::from_utf8(&src.split_to(n + 1)) {
-                Ok(s) => Ok(Some(s.to_string())),
-                Err(err) => Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Failed to format string: {err}"),
-                )),
-            },
+            Some(n) => {
+                let line = src.split_to(n + 1);
+                match str::from_utf8(&line) {
+                    Ok(_) => Ok(Some(line.to_vec())),
+                    Err(err) => Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("Failed to format string: {err}"),
+                    )),
+                }
+            }
             None => Ok(None),
         }
     }
 }
 
-impl Encoder<Vec<u8>> for LineCodec {
+impl Encoder<Vec<u8>> for SerialCodec {
     type Error = io::Error;
 
     fn encode(&mut self, item: Vec<u8>, dst: &mut BytesMut) -> Result<(), Self::Error> {
@@ -80,8 +94,8 @@ fn stop_bits(stop_bits: &u8) -> StopBits {
 }
 
 pub struct SerialSource {
-    write_stream: SplitSink<Framed<SerialStream, LineCodec>, Vec<u8>>,
-    read_stream: SplitStream<Framed<SerialStream, LineCodec>>,
+    write_stream: SplitSink<Framed<SerialStream, SerialCodec>, Vec<u8>>,
+    read_stream: SplitStream<Framed<SerialStream, SerialCodec>>,
     buffer: Buffer,
     amount: usize,
     send_data_delay: u8,
@@ -111,7 +125,7 @@ impl SerialSource {
                         config.path, config.exclusive, err
                     )));
                 }
-                let stream = LineCodec.framed(port);
+                let stream = SerialCodec { raw: config.raw }.framed(port);
                 let (write_stream, read_stream) = stream.split();
                 Ok(Self {
                     write_stream,
@@ -142,7 +156,7 @@ impl ByteSource for SerialSource {
                     if self.amount == 0 {
                         return Ok(None);
                     }
-                    self.buffer.copy_from_slice(received.as_bytes());
+                    self.buffer.copy_from_slice(&received);
                 }
                 Err(err) => {
                     return Err(SourceError::Setup(format!("Failed to read stream: {err}")));
@@ -215,6 +229,24 @@ impl ByteSource for SerialSource {
     }
 }
 
+#[test]
+fn test_serial_codec() {
+    let mut lines = SerialCodec { raw: false };
+    let mut src = BytesMut::from(&b"one\ntw"[..]);
+    assert_eq!(lines.decode(&mut src).unwrap(), Some(b"one\n".to_vec()));
+    assert_eq!(lines.decode(&mut src).unwrap(), None);
+    let mut src = BytesMut::from(&[0xFF, b'\n'][..]);
+    assert!(lines.decode(&mut src).is_err());
+
+    let mut raw = SerialCodec { raw: true };
+    let mut src = BytesMut::from(&[b'D', b'L', b'S', 0x01, 0xFF, b'\n', 0x00][..]);
+    assert_eq!(
+        raw.decode(&mut src).unwrap(),
+        Some(vec![b'D', b'L', b'S', 0x01, 0xFF, b'\n', 0x00])
+    );
+    assert_eq!(raw.decode(&mut src).unwrap(), None);
+}
+
 #[cfg(target_os = "windows")]
 unsafe impl Send for SerialSource {}
 
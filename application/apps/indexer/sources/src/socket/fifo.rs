@@ -0,0 +1,125 @@
+use crate::{ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use async_trait::async_trait;
+use buf_redux::Buffer;
+use std::{
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{io::AsyncReadExt, net::unix::pipe, time::sleep};
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+/// while the pipe has no writer, it's checked for a new one in this interval
+const WRITER_POLL_DELAY: Duration = Duration::from_millis(100);
+
+/// Reads from a named pipe (FIFO). The pipe is opened without blocking once data
+/// is requested and stays open when a writer closes it, so a restarted writer
+/// continues the same stream.
+pub struct FifoSource {
+    path: PathBuf,
+    pipe: Option<pipe::Receiver>,
+    buffer: Buffer,
+    tmp_buffer: Vec<u8>,
+}
+
+impl FifoSource {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        let path = path.as_ref().to_path_buf();
+        if !is_fifo(&path)? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a named pipe", path.display()),
+            ));
+        }
+        Ok(Self {
+            path,
+            pipe: None,
+            buffer: Buffer::new(),
+            tmp_buffer: vec![0u8; READ_CHUNK_SIZE],
+        })
+    }
+}
+
+fn is_fifo(path: &Path) -> Result<bool, std::io::Error> {
+    Ok(std::fs::metadata(path)?.file_type().is_fifo())
+}
+
+#[async_trait]
+impl ByteSource for FifoSource {
+    async fn reload(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        loop {
+            let pipe = match self.pipe.as_mut() {
+                Some(pipe) => pipe,
+                None => {
+                    debug!("Opening {}", self.path.display());
+                    self.pipe
+                        .insert(pipe::OpenOptions::new().open_receiver(&self.path).map_err(
+                            |e| {
+                                SourceError::Setup(format!(
+                                    "Fail to open {}: {e}",
+                                    self.path.display()
+                                ))
+                            },
+                        )?)
+                }
+            };
+            let len = pipe
+                .read(&mut self.tmp_buffer)
+                .await
+                .map_err(SourceError::Io)?;
+            if len == 0 {
+                // there is no writer (yet or anymore)
+                trace!("No writer on {}", self.path.display());
+                sleep(WRITER_POLL_DELAY).await;
+                continue;
+            }
+            trace!("---> Received {} bytes", len);
+            self.buffer.reserve(len);
+            self.buffer.copy_from_slice(&self.tmp_buffer[..len]);
+            return Ok(Some(ReloadInfo::new(len, self.buffer.len(), 0, None)));
+        }
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[tokio::test]
+async fn test_fifo_reload() -> Result<(), std::io::Error> {
+    use std::{io::Write, process::Command};
+    let path = std::env::temp_dir().join(format!("{}.fifo", uuid::Uuid::new_v4()));
+    assert!(Command::new("mkfifo").arg(&path).status()?.success());
+    let mut source = FifoSource::new(&path)?;
+    let writer_path = path.clone();
+    let writer = tokio::task::spawn_blocking(move || -> Result<(), std::io::Error> {
+        // two writers one after another, the source has to survive the first one closing
+        for msg in [b"one\n", b"two\n"] {
+            let mut pipe = std::fs::OpenOptions::new().write(true).open(&writer_path)?;
+            pipe.write_all(msg)?;
+        }
+        Ok(())
+    });
+    let mut received = vec![];
+    while received.len() < 8 {
+        source.reload(None).await.expect("reload failed");
+        received.extend_from_slice(source.current_slice());
+        source.consume(source.len());
+    }
+    assert_eq!(received, b"one\ntwo\n");
+    writer.await.unwrap()?;
+    std::fs::remove_file(&path)?;
+    assert!(FifoSource::new(std::env::temp_dir()).is_err());
+    Ok(())
+}
@@ -1,2 +1,7 @@
+#[cfg(unix)]
+pub mod fifo;
+pub mod syslog;
 pub mod tcp;
 pub mod udp;
+#[cfg(unix)]
+pub mod unix;
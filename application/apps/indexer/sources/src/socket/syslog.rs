@@ -0,0 +1,298 @@
+use crate::{ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use async_trait::async_trait;
+use buf_redux::Buffer;
+use parsers::syslog::SENDER_PREFIX;
+use std::{net::SocketAddr, time::Duration};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader},
+    net::{TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    select,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Error, Debug)]
+pub enum SyslogSourceError {
+    #[error("IO Error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+/// upper limit for octet counted messages, protects against garbage in the length field
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+/// the length of octet counted messages has at most this many digits
+const MAX_FRAME_SIZE_DIGITS: u64 = 10;
+/// receiving or accepting is retried after this delay if it failed
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+/// messages waiting to be read from the source, listeners wait if there are more
+const CHANNEL_CAPACITY: usize = 1024;
+
+type Received = (SocketAddr, Vec<u8>);
+
+/// Listens for syslog messages on UDP and TCP (same port). TCP connections
+/// can use octet counting or LF delimited framing (RFC 6587).
+/// Every received message is provided as one line prefixed with the address of
+/// the sender (see `parsers::syslog::SENDER_PREFIX`).
+pub struct SyslogSource {
+    buffer: Buffer,
+    rx: Receiver<Received>,
+    local_addr: SocketAddr,
+    shutdown: CancellationToken,
+}
+
+impl SyslogSource {
+    pub async fn new<A: ToSocketAddrs>(addr: A) -> Result<Self, SyslogSourceError> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let socket = UdpSocket::bind(local_addr).await?;
+        let (tx, rx) = channel(CHANNEL_CAPACITY);
+        let shutdown = CancellationToken::new();
+        tokio::spawn(receive_datagrams(socket, tx.clone(), shutdown.clone()));
+        tokio::spawn(accept_connections(listener, tx, shutdown.clone()));
+        Ok(Self {
+            buffer: Buffer::new(),
+            rx,
+            local_addr,
+            shutdown,
+        })
+    }
+
+    /// the address the listeners are bound to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for SyslogSource {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn receive_datagrams(socket: UdpSocket, tx: Sender<Received>, shutdown: CancellationToken) {
+    let mut tmp_buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        let (len, sender) = select! {
+            received = socket.recv_from(&mut tmp_buffer) => match received {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("Fail to receive syslog datagram: {e}");
+                    select! {
+                        _ = sleep(RETRY_DELAY) => continue,
+                        _ = shutdown.cancelled() => break,
+                    }
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        };
+        if len > 0 && tx.send((sender, tmp_buffer[..len].to_vec())).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    tx: Sender<Received>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let (stream, sender) = select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Fail to accept syslog connection: {e}");
+                    select! {
+                        _ = sleep(RETRY_DELAY) => continue,
+                        _ = shutdown.cancelled() => break,
+                    }
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        };
+        debug!("Syslog connection from {sender}");
+        tokio::spawn(read_connection(
+            stream,
+            sender,
+            tx.clone(),
+            shutdown.clone(),
+        ));
+    }
+}
+
+async fn read_connection(
+    stream: TcpStream,
+    sender: SocketAddr,
+    tx: Sender<Received>,
+    shutdown: CancellationToken,
+) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let frame = select! {
+            frame = read_frame(&mut reader) => frame,
+            _ = shutdown.cancelled() => break,
+        };
+        match frame {
+            Ok(Some(msg)) => {
+                if tx.send((sender, msg)).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => {
+                debug!("Syslog connection from {sender} closed");
+                break;
+            }
+            Err(e) => {
+                warn!("Syslog connection from {sender} dropped: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// reads the next message from a stream. Messages starting with a digit are
+/// taken as octet counted (`MSG-LEN SP SYSLOG-MSG`), all others are LF delimited.
+/// Both are limited to `MAX_FRAME_SIZE`.
+async fn read_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let octet_counting = match reader.fill_buf().await?.first() {
+        Some(first) => first.is_ascii_digit(),
+        None => return Ok(None),
+    };
+    if octet_counting {
+        let mut len = Vec::new();
+        (&mut *reader)
+            .take(MAX_FRAME_SIZE_DIGITS + 1)
+            .read_until(b' ', &mut len)
+            .await?;
+        let len = std::str::from_utf8(&len)
+            .ok()
+            .and_then(|len| len.strip_suffix(' '))
+            .and_then(|len| len.parse::<usize>().ok())
+            .filter(|len| *len <= MAX_FRAME_SIZE)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid frame length")
+            })?;
+        let mut msg = vec![0u8; len];
+        reader.read_exact(&mut msg).await?;
+        Ok(Some(msg))
+    } else {
+        let mut msg = Vec::new();
+        let len = (&mut *reader)
+            .take(MAX_FRAME_SIZE as u64)
+            .read_until(b'\n', &mut msg)
+            .await?;
+        if len == 0 {
+            return Ok(None);
+        }
+        if len == MAX_FRAME_SIZE && !msg.ends_with(b"\n") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "frame too long",
+            ));
+        }
+        Ok(Some(msg))
+    }
+}
+
+#[async_trait]
+impl ByteSource for SyslogSource {
+    async fn reload(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        let (sender, mut msg) = match self.rx.recv().await {
+            Some(received) => received,
+            None => return Ok(None),
+        };
+        while matches!(msg.last(), Some(b'\n' | b'\r' | b'\0')) {
+            msg.pop();
+        }
+        // one message has to stay one line
+        for byte in msg.iter_mut().filter(|b| **b == b'\n') {
+            *byte = b' ';
+        }
+        let mut line = format!("{SENDER_PREFIX}{sender} ").into_bytes();
+        line.extend_from_slice(&msg);
+        line.push(b'\n');
+        self.buffer.reserve(line.len());
+        self.buffer.copy_from_slice(&line);
+        Ok(Some(ReloadInfo::new(
+            line.len(),
+            self.buffer.len(),
+            0,
+            None,
+        )))
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    async fn cancel(&mut self) -> Result<(), SourceError> {
+        self.shutdown.cancel();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_read_frame() {
+    let mut input: &[u8] = b"11 <13>one\ntwo<14>two\n<15>three";
+    assert_eq!(
+        read_frame(&mut input).await.unwrap(),
+        Some(b"<13>one\ntwo".to_vec())
+    );
+    assert_eq!(
+        read_frame(&mut input).await.unwrap(),
+        Some(b"<14>two\n".to_vec())
+    );
+    assert_eq!(
+        read_frame(&mut input).await.unwrap(),
+        Some(b"<15>three".to_vec())
+    );
+    assert_eq!(read_frame(&mut input).await.unwrap(), None);
+    // the length is not searched for in an endless stream of digits
+    let mut input: &[u8] = b"12345678901 <13>one";
+    assert!(read_frame(&mut input).await.is_err());
+    let mut input: &[u8] = b"12345";
+    assert!(read_frame(&mut input).await.is_err());
+    // a line without end is not read endlessly either
+    let long = vec![b'x'; MAX_FRAME_SIZE + 1];
+    assert!(read_frame(&mut long.as_slice()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_syslog_reload() -> Result<(), SyslogSourceError> {
+    use tokio::io::AsyncWriteExt;
+    let mut source = SyslogSource::new("127.0.0.1:0").await?;
+    let addr = source.local_addr();
+
+    let udp = UdpSocket::bind("127.0.0.1:0").await?;
+    udp.send_to(b"<13>via udp", addr).await?;
+    source.reload(None).await.expect("reload failed");
+    let expected = format!("@{} <13>via udp\n", udp.local_addr()?);
+    assert_eq!(source.current_slice(), expected.as_bytes());
+    source.consume(expected.len());
+
+    let mut tcp = TcpStream::connect(addr).await?;
+    tcp.write_all(b"13 <14>via\ntcp 1<15>via tcp 2\n").await?;
+    tcp.flush().await?;
+    for msg in ["<14>via tcp 1", "<15>via tcp 2"] {
+        source.reload(None).await.expect("reload failed");
+        let expected = format!("@{} {msg}\n", tcp.local_addr()?);
+        assert_eq!(source.current_slice(), expected.as_bytes());
+        source.consume(expected.len());
+    }
+    Ok(())
+}
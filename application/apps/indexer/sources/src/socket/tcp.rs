@@ -1,15 +1,41 @@
-use crate::{ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use crate::{
+    factory::TcpReconnectConfig, ByteSource, Error as SourceError, ReloadInfo, SourceFilter,
+};
 use async_trait::async_trait;
 use buf_redux::Buffer;
-use tokio::net::{TcpStream, ToSocketAddrs};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::UnboundedSender,
+    time::sleep,
+};
 
 pub struct TcpSource {
     buffer: Buffer,
     socket: TcpStream,
     tmp_buffer: Vec<u8>,
+    reconnect: Option<Reconnect>,
 }
 
 const MAX_DATAGRAM_SIZE: usize = 65_507;
+/// the time between reconnect attempts doesn't grow beyond this
+const MAX_RECONNECT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// reported while a lost connection is reestablished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TcpEvent {
+    Disconnected(String),
+    /// connected again after the given number of attempts
+    Reconnected(u32),
+    /// no connection after the given number of attempts, the source ends
+    GaveUp(u32),
+}
+
+struct Reconnect {
+    addr: SocketAddr,
+    config: TcpReconnectConfig,
+    events: UnboundedSender<TcpEvent>,
+}
 
 impl TcpSource {
     pub async fn new<A: ToSocketAddrs>(addr: A) -> Result<Self, std::io::Error> {
@@ -17,25 +43,143 @@ impl TcpSource {
             buffer: Buffer::new(),
             socket: TcpStream::connect(addr).await?,
             tmp_buffer: vec![0u8; MAX_DATAGRAM_SIZE],
+            reconnect: None,
         })
     }
+
+    /// connect again to the same address if the connection is lost,
+    /// the progress of reconnecting is reported to `events`
+    pub fn with_reconnect(
+        mut self,
+        config: TcpReconnectConfig,
+        events: UnboundedSender<TcpEvent>,
+    ) -> Result<Self, std::io::Error> {
+        self.reconnect = Some(Reconnect {
+            addr: self.socket.peer_addr()?,
+            config,
+            events,
+        });
+        Ok(self)
+    }
+
+    /// tries to reestablish the connection as configured. Without a reconnect
+    /// configuration the source cannot continue.
+    async fn reconnect(&mut self, reason: String) -> Result<(), SourceError> {
+        let reconnect = match self.reconnect.as_ref() {
+            Some(reconnect) => reconnect,
+            None => return Err(SourceError::Setup(reason)),
+        };
+        warn!("TCP connection to {} lost: {reason}", reconnect.addr);
+        let _ = reconnect.events.send(TcpEvent::Disconnected(reason));
+        let mut interval = Duration::from_millis(reconnect.config.interval_ms);
+        let mut attempts = 0;
+        loop {
+            if matches!(reconnect.config.max_attempts, Some(max) if attempts >= max) {
+                let _ = reconnect.events.send(TcpEvent::GaveUp(attempts));
+                return Err(SourceError::Unrecoverable(format!(
+                    "Fail to reconnect to {} after {attempts} attempts",
+                    reconnect.addr
+                )));
+            }
+            sleep(interval).await;
+            attempts += 1;
+            match TcpStream::connect(reconnect.addr).await {
+                Ok(socket) => {
+                    debug!(
+                        "Reconnected to {} after {attempts} attempts",
+                        reconnect.addr
+                    );
+                    let _ = reconnect.events.send(TcpEvent::Reconnected(attempts));
+                    self.socket = socket;
+                    return Ok(());
+                }
+                Err(e) => {
+                    debug!(
+                        "Reconnect attempt {attempts} to {} failed: {e}",
+                        reconnect.addr
+                    );
+                    interval = interval
+                        .mul_f32(reconnect.config.backoff.max(1.0))
+                        .min(MAX_RECONNECT_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// a connection has a single sender, its data is only used if that sender
+    /// matches the filter
+    fn accepts(&self, filter: Option<&SourceFilter>) -> bool {
+        match (filter, self.socket.peer_addr()) {
+            (None, _) => true,
+            (Some(filter), Ok(peer)) => filter.matches_sender(&peer),
+            (Some(_), Err(_)) => false,
+        }
+    }
+
+    /// create a source for a connection that was accepted by a [`TcpServer`]
+    pub fn from_stream(socket: TcpStream) -> Self {
+        Self {
+            buffer: Buffer::new(),
+            socket,
+            tmp_buffer: vec![0u8; MAX_DATAGRAM_SIZE],
+            reconnect: None,
+        }
+    }
+}
+
+/// Listens for incoming connections. Every accepted connection is
+/// provided as its own [`TcpSource`]
+pub struct TcpServer {
+    listener: TcpListener,
+}
+
+impl TcpServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        self.listener.local_addr()
+    }
+
+    /// waits for the next client and returns a source for its connection
+    /// together with the address of the client
+    pub async fn accept(&self) -> Result<(TcpSource, SocketAddr), std::io::Error> {
+        let (socket, peer) = self.listener.accept().await?;
+        Ok((TcpSource::from_stream(socket), peer))
+    }
 }
 
 #[async_trait]
 impl ByteSource for TcpSource {
     async fn reload(
         &mut self,
-        _filter: Option<&SourceFilter>,
+        filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        // TODO use filter
         loop {
             debug!("Wait for tcp socket to become readable");
-            self.socket
-                .readable()
-                .await
-                .map_err(|e| SourceError::Unrecoverable(format!("{e}")))?;
+            if let Err(e) = self.socket.readable().await {
+                if self.reconnect.is_none() {
+                    return Err(SourceError::Unrecoverable(format!("{e}")));
+                }
+                self.reconnect(format!("{e}")).await?;
+                continue;
+            }
             debug!("Socket ready to read");
             match self.socket.try_read(&mut self.tmp_buffer) {
+                Ok(0) if self.reconnect.is_some() => {
+                    self.reconnect(String::from("connection closed by peer"))
+                        .await?;
+                }
+                Ok(len) if len > 0 && !self.accepts(filter) => {
+                    trace!(
+                        "---> Skipped {} bytes from {:?}",
+                        len,
+                        self.socket.peer_addr()
+                    );
+                }
                 Ok(len) => {
                     trace!("---> Received {} bytes", len);
                     if len > 0 {
@@ -48,7 +192,7 @@ impl ByteSource for TcpSource {
                     continue;
                 }
                 Err(e) => {
-                    return Err(SourceError::Setup(format!("{e}")));
+                    self.reconnect(format!("{e}")).await?;
                 }
             }
         }
@@ -111,3 +255,94 @@ async fn test_tcp_reload() -> Result<(), std::io::Error> {
     assert!(rec_res.is_ok());
     Ok(())
 }
+
+#[tokio::test]
+async fn test_tcp_server() -> Result<(), std::io::Error> {
+    use tokio::io::AsyncWriteExt;
+    let server = TcpServer::bind("127.0.0.1:0").await?;
+    let addr = server.local_addr()?;
+    let mut clients = vec![];
+    for msg in ["first", "second"] {
+        let mut client = TcpStream::connect(addr).await?;
+        client.write_all(msg.as_bytes()).await?;
+        client.flush().await?;
+        let (mut source, peer) = server.accept().await?;
+        assert_eq!(peer, client.local_addr()?);
+        source.reload(None).await.expect("reload failed");
+        assert_eq!(source.current_slice(), msg.as_bytes());
+        clients.push(client);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_reconnect() -> Result<(), std::io::Error> {
+    use tokio::{io::AsyncWriteExt, sync::mpsc::unbounded_channel};
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let (tx_events, mut rx_events) = unbounded_channel();
+    let config = TcpReconnectConfig {
+        interval_ms: 10,
+        backoff: 2.0,
+        max_attempts: Some(3),
+    };
+    let mut source = TcpSource::new(addr)
+        .await?
+        .with_reconnect(config, tx_events)?;
+    let (mut first, _) = listener.accept().await?;
+    first.write_all(b"one").await?;
+    drop(first);
+    source.reload(None).await.expect("reload failed");
+    assert_eq!(source.current_slice(), b"one");
+    source.consume(3);
+    let server = tokio::spawn(async move {
+        let (mut second, _) = listener.accept().await.unwrap();
+        second.write_all(b"two").await.unwrap();
+        second.flush().await.unwrap();
+        // keep the listener alive until the source read the data
+        sleep(Duration::from_millis(200)).await;
+    });
+    source
+        .reload(None)
+        .await
+        .expect("reload after reconnect failed");
+    assert_eq!(source.current_slice(), b"two");
+    assert!(matches!(
+        rx_events.recv().await,
+        Some(TcpEvent::Disconnected(_))
+    ));
+    assert_eq!(rx_events.recv().await, Some(TcpEvent::Reconnected(1)));
+    server.await.unwrap();
+    // listener is gone now, reconnecting has to give up
+    assert!(source.reload(None).await.is_err());
+    assert!(matches!(
+        rx_events.recv().await,
+        Some(TcpEvent::Disconnected(_))
+    ));
+    assert_eq!(rx_events.recv().await, Some(TcpEvent::GaveUp(3)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_filter() -> Result<(), std::io::Error> {
+    use tokio::io::AsyncWriteExt;
+    let server = TcpServer::bind("127.0.0.1:0").await?;
+    let addr = server.local_addr()?;
+    let mut client = TcpStream::connect(addr).await?;
+    let (mut source, peer) = server.accept().await?;
+    let other = SourceFilter {
+        source_port: Some(peer.port().wrapping_add(1)),
+        ..Default::default()
+    };
+    assert!(!source.accepts(Some(&other)));
+    let matching = SourceFilter {
+        source_port: Some(peer.port()),
+        ..Default::default()
+    };
+    assert!(source.accepts(Some(&matching)));
+    client.write_all(b"data").await?;
+    client.flush().await?;
+    source.reload(Some(&matching)).await.expect("reload failed");
+    assert_eq!(source.current_slice(), b"data");
+    Ok(())
+}
@@ -79,14 +79,20 @@ impl UdpSource {
 impl ByteSource for UdpSource {
     async fn reload(
         &mut self,
-        _filter: Option<&SourceFilter>,
+        filter: Option<&SourceFilter>,
     ) -> Result<Option<ReloadInfo>, SourceError> {
-        // TODO use filter
-        let (len, remote_addr) = self
-            .socket
-            .recv_from(&mut self.tmp_buffer)
-            .await
-            .map_err(|e| SourceError::Setup(format!("{e}")))?;
+        let (len, remote_addr) = loop {
+            let (len, remote_addr) = self
+                .socket
+                .recv_from(&mut self.tmp_buffer)
+                .await
+                .map_err(|e| SourceError::Setup(format!("{e}")))?;
+            if filter.is_some_and(|filter| !filter.matches_sender(&remote_addr)) {
+                trace!("---> Skipped {} bytes from {:?}", len, remote_addr);
+                continue;
+            }
+            break (len, remote_addr);
+        };
         trace!(
             "---> Received {} bytes from {:?}: {}",
             len,
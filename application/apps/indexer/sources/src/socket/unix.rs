@@ -0,0 +1,124 @@
+use crate::{ByteSource, Error as SourceError, ReloadInfo, SourceFilter};
+use async_trait::async_trait;
+use buf_redux::Buffer;
+use std::path::{Path, PathBuf};
+use tokio::{
+    io::AsyncReadExt,
+    net::{UnixDatagram, UnixStream},
+};
+
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+enum Socket {
+    Stream(UnixStream),
+    /// the socket file is created by us and removed again once we are done
+    Datagram(UnixDatagram, PathBuf),
+}
+
+/// Reads from a unix domain socket. Stream sockets are connected to the
+/// given path, for datagram sockets the path is bound to receive from it.
+pub struct UnixSocketSource {
+    buffer: Buffer,
+    socket: Socket,
+    tmp_buffer: Vec<u8>,
+}
+
+impl UnixSocketSource {
+    pub async fn connect<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            buffer: Buffer::new(),
+            socket: Socket::Stream(UnixStream::connect(path).await?),
+            tmp_buffer: vec![0u8; MAX_DATAGRAM_SIZE],
+        })
+    }
+
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        let path = path.as_ref().to_path_buf();
+        Ok(Self {
+            buffer: Buffer::new(),
+            socket: Socket::Datagram(UnixDatagram::bind(&path)?, path),
+            tmp_buffer: vec![0u8; MAX_DATAGRAM_SIZE],
+        })
+    }
+}
+
+impl Drop for UnixSocketSource {
+    fn drop(&mut self) {
+        if let Socket::Datagram(_, path) = &self.socket {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!("Fail to remove socket file {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ByteSource for UnixSocketSource {
+    async fn reload(
+        &mut self,
+        _filter: Option<&SourceFilter>,
+    ) -> Result<Option<ReloadInfo>, SourceError> {
+        let len = match &mut self.socket {
+            Socket::Stream(stream) => stream.read(&mut self.tmp_buffer).await,
+            Socket::Datagram(socket, _) => socket.recv(&mut self.tmp_buffer).await,
+        }
+        .map_err(|e| SourceError::Setup(format!("{e}")))?;
+        trace!("---> Received {} bytes", len);
+        if len > 0 {
+            self.buffer.reserve(len);
+            self.buffer.copy_from_slice(&self.tmp_buffer[..len]);
+        }
+        Ok(Some(ReloadInfo::new(len, len, 0, None)))
+    }
+
+    fn current_slice(&self) -> &[u8] {
+        self.buffer.buf()
+    }
+
+    fn consume(&mut self, offset: usize) {
+        self.buffer.consume(offset)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[cfg(test)]
+fn socket_path() -> PathBuf {
+    std::env::temp_dir().join(format!("{}.sock", uuid::Uuid::new_v4()))
+}
+
+#[tokio::test]
+async fn test_stream_reload() -> Result<(), std::io::Error> {
+    use tokio::{io::AsyncWriteExt, net::UnixListener};
+    let path = socket_path();
+    let listener = UnixListener::bind(&path)?;
+    let mut source = UnixSocketSource::connect(&path).await?;
+    let (mut stream, _) = listener.accept().await?;
+    stream.write_all(&[0x00, 0xFF, b'\n', 0x01]).await?;
+    source.reload(None).await.expect("reload failed");
+    assert_eq!(source.current_slice(), &[0x00, 0xFF, b'\n', 0x01]);
+    drop(stream);
+    source.consume(4);
+    let info = source.reload(None).await.expect("reload failed").unwrap();
+    assert_eq!(info.newly_loaded_bytes, 0);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_datagram_reload() -> Result<(), std::io::Error> {
+    let path = socket_path();
+    let mut source = UnixSocketSource::bind(&path)?;
+    let sender = UnixDatagram::unbound()?;
+    for msg in ["one", "two"] {
+        sender.send_to(msg.as_bytes(), &path).await?;
+        source.reload(None).await.expect("reload failed");
+        assert_eq!(source.current_slice(), msg.as_bytes());
+        source.consume(msg.len());
+    }
+    drop(source);
+    assert!(!path.exists());
+    Ok(())
+}
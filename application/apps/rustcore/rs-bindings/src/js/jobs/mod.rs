@@ -181,6 +181,21 @@ impl UnboundJobs {
             .map(CommandOutcomeWrapper)
     }
 
+    #[node_bindgen]
+    async fn get_archive_members(
+        &self,
+        id: i64,
+        path: String,
+    ) -> Result<CommandOutcomeWrapper<String>, ComputationErrorWrapper> {
+        self.api
+            .as_ref()
+            .ok_or(ComputationError::SessionUnavailable)?
+            .get_archive_members(u64_from_i64(id)?, path)
+            .await
+            .map_err(ComputationErrorWrapper)
+            .map(CommandOutcomeWrapper)
+    }
+
     #[node_bindgen]
     async fn get_dlt_stats(
         &self,
@@ -3,6 +3,7 @@ import { Base } from '../native/native.jobs';
 import { error } from 'platform/log/utils';
 import { IFilter } from 'platform/types/filter';
 import { ShellProfile } from 'platform/types/shells';
+import { ArchiveMember } from 'platform/types/files';
 import { SomeipStatistic } from 'platform/types/observe/parser/someip';
 import { StatisticInfo } from 'platform/types/observe/parser/dlt';
 
@@ -112,6 +113,23 @@ export class Jobs extends Base {
         return job;
     }
 
+    public getArchiveMembers(path: string): CancelablePromise<ArchiveMember[]> {
+        const sequence = this.sequence();
+        const job: CancelablePromise<ArchiveMember[]> = this.execute(
+            (res: string): ArchiveMember[] | Error => {
+                try {
+                    return JSON.parse(res) as ArchiveMember[];
+                } catch (e) {
+                    return new Error(error(e));
+                }
+            },
+            this.native.getArchiveMembers(sequence, path),
+            sequence,
+            'getArchiveMembers',
+        );
+        return job;
+    }
+
     public getDltStats(paths: string[]): CancelablePromise<StatisticInfo> {
         const sequence = this.sequence();
         const job: CancelablePromise<StatisticInfo> = this.execute(
@@ -70,6 +70,11 @@ export interface File {
     stat: Stat;
 }
 
+export interface ArchiveMember {
+    name: string;
+    size: number;
+}
+
 export interface Stat {
     dev: number;
     ino: number;
@@ -64,7 +64,11 @@ export interface IConfiguration {
     filter_config: IFilters | undefined;
     fibex_file_paths: string[] | undefined;
     with_storage_header: boolean;
+    // Messages start with the serial header (DLS + 0x01) as sent over serial lines
+    with_serial_header?: boolean;
     tz: string | undefined;
+    // FIBEX files to decode SOME/IP messages of network traces
+    someip_fibex_file_paths?: string[];
 }
 
 @Statics<ConfigurationStaticDesc<IConfiguration, Protocol>>()
@@ -87,7 +91,11 @@ export class Configuration
     static validate(configuration: IConfiguration): Error | IConfiguration {
         try {
             obj.getAsBool(configuration, 'with_storage_header');
+            if (configuration.with_serial_header !== undefined) {
+                obj.getAsBool(configuration, 'with_serial_header');
+            }
             obj.getAsNotEmptyStringsArrayOrUndefined(configuration, 'fibex_file_paths');
+            obj.getAsNotEmptyStringsArrayOrUndefined(configuration, 'someip_fibex_file_paths');
             obj.getAsObjOrUndefined(configuration, 'filter_config');
             obj.getAsNotEmptyStringOrAsUndefined(configuration, 'tz');
             const filter_config = configuration.filter_config;
@@ -111,6 +119,7 @@ export class Configuration
             filter_config: undefined,
             fibex_file_paths: [],
             with_storage_header: true,
+            with_serial_header: false,
             tz: undefined,
         };
     }
@@ -191,7 +200,12 @@ export class Configuration
             `dlt:${(this.configuration.fibex_file_paths === undefined
                 ? []
                 : this.configuration.fibex_file_paths
-            ).join(';')};${this.configuration.with_storage_header};${this.configuration.tz};${
+            ).join(';')};${(this.configuration.someip_fibex_file_paths === undefined
+                ? []
+                : this.configuration.someip_fibex_file_paths
+            ).join(';')};${this.configuration.with_storage_header};${
+                this.configuration.with_serial_header === true
+            };${this.configuration.tz};${
                 filters.min_log_level
             };${filters.ecu_ids?.length};${filters.app_ids?.length};${filters.context_ids?.length}`,
         );
@@ -15,6 +15,10 @@ export interface SomeipStatistic {
     services: SomeipStatisticItem[];
     /** Statistic on message-types and related return-codes */
     messages: SomeipStatisticItem[];
+    /** Statistic on service discovery entries per service instance */
+    instances?: SomeipStatisticInstance[];
+    /** Endpoints given by service discovery entries */
+    endpoints?: string[];
 }
 
 export interface SomeipStatisticItem {
@@ -27,8 +31,38 @@ export interface SomeipStatisticDetail {
     num: number;
 }
 
+export interface SomeipStatisticInstance {
+    service_id: number;
+    instance_id: number;
+    finds: number;
+    offers: number;
+    stop_offers: number;
+    subscriptions: number;
+    stop_subscriptions: number;
+    subscription_acks: number;
+    subscription_nacks: number;
+    /** Subscribed eventgroup-ids */
+    eventgroups: number[];
+    endpoints: string[];
+}
+
+export enum SomeipMessageKind {
+    Sd = 'Sd',
+    Rpc = 'Rpc',
+}
+
+export interface IFilters {
+    service_ids?: number[];
+    method_ids?: number[];
+    message_types?: number[];
+    return_codes?: number[];
+    message_kind?: SomeipMessageKind;
+}
+
 export interface IConfiguration {
     fibex_file_paths: string[] | undefined;
+    // Messages not matching the filters are dropped while parsing
+    filter_config?: IFilters;
 }
 
 @Statics<ConfigurationStaticDesc<IConfiguration, Protocol>>()
@@ -51,6 +85,15 @@ export class Configuration
     static validate(configuration: IConfiguration): Error | IConfiguration {
         try {
             obj.getAsNotEmptyStringsArrayOrUndefined(configuration, 'fibex_file_paths');
+            obj.getAsObjOrUndefined(configuration, 'filter_config');
+            const filter_config = configuration.filter_config;
+            if (
+                filter_config !== undefined &&
+                filter_config.message_kind !== undefined &&
+                !Object.values(SomeipMessageKind).includes(filter_config.message_kind)
+            ) {
+                throw new Error(`Invalid message kind: ${filter_config.message_kind}`);
+            }
             return configuration;
         } catch (e) {
             return new Error(error(e));
@@ -77,7 +120,7 @@ export class Configuration
             `someip:${(this.configuration.fibex_file_paths === undefined
                 ? []
                 : this.configuration.fibex_file_paths
-            ).join(';')}`,
+            ).join(';')};${JSON.stringify(this.configuration.filter_config ?? {})}`,
         );
     }
 }
@@ -4,12 +4,31 @@ import { Configuration as Base, ConfigurationStaticDesc } from '../../configurat
 import { Statics } from '../../../../env/decorators';
 import { List, IList } from '../../description';
 
+import * as obj from '../../../../env/obj';
 import * as str from '../../../../env/str';
 import * as Origin from '../../origin/index';
 import * as Stream from '../../origin/stream/index';
 import * as Files from '../../types/file';
 
-export type IConfiguration = null;
+export enum TextEncoding {
+    Auto = 'Auto',
+    Utf8 = 'Utf8',
+    Utf16Le = 'Utf16Le',
+    Utf16Be = 'Utf16Be',
+    Latin1 = 'Latin1',
+    Windows1252 = 'Windows1252',
+    ShiftJis = 'ShiftJis',
+}
+
+export interface ITextSettings {
+    // Detected by BOM if not defined
+    encoding?: TextEncoding;
+    // Lines which don't match this regex are appended to the previous record
+    record_start?: string;
+}
+
+// null is the same as settings with defaults only
+export type IConfiguration = ITextSettings | null;
 
 @Statics<ConfigurationStaticDesc<IConfiguration, Protocol>>()
 export class Configuration
@@ -30,9 +49,19 @@ export class Configuration
 
     static validate(configuration: IConfiguration): Error | IConfiguration {
         try {
-            if (configuration !== null) {
-                throw new Error(`Text parser doesn't have any configuration; it should null`);
+            if (configuration === null) {
+                return configuration;
             }
+            if (typeof configuration !== 'object') {
+                throw new Error(`Text parser configuration should be an object or null`);
+            }
+            if (
+                configuration.encoding !== undefined &&
+                !Object.values(TextEncoding).includes(configuration.encoding)
+            ) {
+                throw new Error(`Invalid text encoding: ${configuration.encoding}`);
+            }
+            obj.getAsNotEmptyStringOrAsUndefined(configuration, 'record_start');
             return configuration;
         } catch (e) {
             return new Error(error(e));
@@ -53,6 +82,13 @@ export class Configuration
     }
 
     public override hash(): number {
-        return str.hash(`text`);
+        if (this.configuration === null) {
+            return str.hash(`text`);
+        }
+        return str.hash(
+            `text:${this.configuration.encoding ?? TextEncoding.Auto};${
+                this.configuration.record_start ?? ''
+            }`,
+        );
     }
 }